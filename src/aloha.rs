@@ -0,0 +1,244 @@
+use rand::StdRng;
+use backoff::{Backoff, BackoffPolicy, TRUNCATION};
+use log;
+use mac::{MacProtocol, Station};
+use rng::{self, Stream};
+use simulators::{Packet, Timing, MAX_ATTEMPTS};
+
+// aloha::throughput returns the throughput S, as a fraction of the LAN speed, that theory predicts
+// for the given offered load G, in transmission attempts per frame time: G * e^-2G for pure ALOHA,
+// peaking at 1/2e (18.4%) for G = 0.5, and G * e^-G for slotted ALOHA, peaking at 1/e (36.8%)
+// for G = 1.
+pub fn throughput(slotted: bool, load: f64) -> f64 {
+    if slotted {
+        load * (-load).exp()
+    } else {
+        load * (-2.0 * load).exp()
+    }
+}
+
+// WINDOW is the default backoff window of ALOHA nodes, in slots.
+pub const WINDOW: u32 = 20;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum State {
+    Idle,
+    Waiting {
+        until: u32,
+        current_packet: Packet,
+        retries: u32,
+    },
+    Transmitting {
+        bits_processed: f64,
+        collided: bool,
+        current_packet: Packet,
+        retries: u32,
+    },
+}
+
+// Aloha is the ALOHA MacProtocol. Nodes transmit without sensing the medium nor detecting
+// collisions, right away (pure ALOHA) or at the start of the next slot (slotted ALOHA). A
+// transmission fails if any other signal reaches the node while it's transmitting, the node then
+// waiting a random number of slots, as per its backoff policy, before retransmitting. Packets
+// queued up behind others wait as long as they would have after a first failed attempt rather
+// than follow one another back to back, every node with packets to send being as likely to
+// transmit as any other, as theory has it.
+pub struct Aloha {
+    state: State,
+    timing: Timing,
+    slot: u32,
+    slotted: bool,
+    backoff: Box<dyn Backoff>,
+    attempts: u32,
+    rng: StdRng,
+}
+
+impl Aloha {
+    // Aloha::new returns an idle Aloha for the node with the given id, slots being the given
+    // number of ticks long, the time it takes to transmit a frame and for it to propagate to
+    // every node. Its backoff draws from the node's MAC stream of the master seed (see
+    // rng::stream).
+    pub fn new(id: usize, timing: Timing, slot: u32, slotted: bool, seed: u64) -> Aloha {
+        Aloha {
+            state: State::Idle,
+            timing,
+            slot: slot.max(1),
+            slotted,
+            backoff: BackoffPolicy::Constant(WINDOW).build(TRUNCATION),
+            attempts: MAX_ATTEMPTS,
+            rng: rng::stream(seed, id, Stream::Mac),
+        }
+    }
+
+    // Aloha.with_backoff sets the backoff policy, a constant window of WINDOW slots by default.
+    pub fn with_backoff(mut self, backoff: Box<dyn Backoff>) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    // Aloha.with_attempts sets the number of failed attempts at transmitting a packet after which
+    // it's dropped, MAX_ATTEMPTS by default.
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    // Aloha.start returns the first tick, from the given one on, a transmission may start at.
+    fn start(&self, tick: u32) -> u32 {
+        if self.slotted {
+            tick.div_ceil(self.slot).saturating_mul(self.slot)
+        } else {
+            tick
+        }
+    }
+
+    // Aloha.backoff_or_drop waits for a random number of slots before retransmitting the
+    // current packet, having failed to transmit it the given number of times, or drops it
+    // altogether once out of attempts.
+    fn backoff_or_drop(&mut self, station: &mut Station, current_packet: Packet, retries: u32) {
+        if retries >= self.attempts {
+            station.log(log::Kind::Dropped, &current_packet, retries);
+            station.statistics.packets_dropped += 1;
+            self.state = State::Idle;
+        } else {
+            let wait_time = self.backoff.slots(&mut self.rng, retries).saturating_mul(self.slot);
+            station.log(log::Kind::Backoff, &current_packet, retries);
+            self.state = State::Waiting {
+                until: self.start(station.now + 1).saturating_add(wait_time),
+                current_packet,
+                retries,
+            };
+        }
+    }
+}
+
+impl MacProtocol for Aloha {
+    fn tick(&mut self, station: &mut Station) -> Option<(Packet, u32)> {
+        loop {
+            match self.state {
+                State::Idle => {
+                    match station.queue.pop() {
+                        Some(packet) => {
+                            let mut until = station.now;
+                            if packet.time_generated < station.now {
+                                let slots = self.backoff.slots(&mut self.rng, 1);
+                                until = until.saturating_add(slots.saturating_mul(self.slot));
+                            }
+                            self.state = State::Waiting {
+                                until: self.start(until),
+                                current_packet: packet,
+                                retries: 0,
+                            }
+                        }
+                        None => break,
+                    }
+                }
+                State::Waiting {
+                    until,
+                    current_packet,
+                    retries,
+                } => {
+                    if station.now < until {
+                        break;
+                    }
+                    station.log(log::Kind::Transmitting, &current_packet, retries);
+                    self.state = State::Transmitting {
+                        bits_processed: 0.0,
+                        collided: false,
+                        current_packet,
+                        retries,
+                    };
+                }
+                State::Transmitting {
+                    bits_processed,
+                    collided,
+                    current_packet,
+                    retries,
+                } => {
+                    let bits_processed = bits_processed + self.timing.bits_per_tick();
+                    let collided = collided || station.busy();
+                    station.transmit();
+                    if (bits_processed as u32) < current_packet.length {
+                        self.state = State::Transmitting {
+                            bits_processed,
+                            collided,
+                            current_packet,
+                            retries,
+                        };
+                        break;
+                    }
+
+                    if !collided {
+                        self.state = State::Idle;
+                        return Some((current_packet, retries));
+                    }
+                    station.statistics.collisions += 1;
+                    station.log(log::Kind::Collision, &current_packet, retries);
+                    self.backoff_or_drop(station, current_packet, retries + 1);
+                    break;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::E;
+    use backoff::BackoffPolicy;
+    use engine::{self, Params};
+    use mac::Mac;
+    use sizes::PacketSize;
+
+    #[test]
+    fn theoretical_peaks() {
+        assert!((throughput(false, 0.5) - 1.0 / (2.0 * E)).abs() < 1e-12);
+        assert!((throughput(true, 1.0) - 1.0 / E).abs() < 1e-12);
+        for &load in &[0.25, 0.45, 0.55, 2.0] {
+            assert!(throughput(false, load) < throughput(false, 0.5));
+        }
+        for &load in &[0.5, 0.9, 1.1, 4.0] {
+            assert!(throughput(true, load) < throughput(true, 1.0));
+        }
+    }
+
+    // The throughput S relates to the offered load G as theory predicts, slotted ALOHA halving
+    // the window of vulnerability of pure ALOHA. Every node always has packets to send, the
+    // backoff window setting G.
+    #[test]
+    fn throughput_against_offered_load() {
+        // Packets take 20 ticks to transmit, slots an extra tick to propagate. Finite as the
+        // population of nodes is, they fare slightly better than theory has it.
+        let lan = |mac, window| {
+            engine::run(&Params {
+                rate: 1000,
+                psize: PacketSize::Constant(20),
+                qsize: Some(4),
+                lspeed: 10_000,
+                resolution: 1e4,
+                duration: 20,
+                ncount: 25,
+                mac,
+                backoff: Some(BackoffPolicy::Constant(window)),
+                attempts: 1000,
+                seed: 12,
+                ..Params::default()
+            })
+        };
+        for &(mac, window, expected) in &[
+            (Mac::Aloha, 90, 0.5),
+            (Mac::Aloha, 45, 1.0),
+            (Mac::SlottedAloha, 45, 1.0),
+        ]
+        {
+            let report = lan(mac, window);
+            let load = report.offered_load();
+            assert!((load - expected).abs() < 0.1, "{} at G = {}", mac, load);
+            assert!(load > report.efficiency());
+            let throughput = mac.throughput(load).unwrap();
+            assert!((report.efficiency() - throughput).abs() < 0.02, "{} at G = {}", mac, load);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::str::FromStr;
+use rand::{Rng, StdRng};
+use serde::{Serialize, Serializer};
+
+// TRUNCATION is the default number of retries past which the contention window stops growing,
+// 10 as per IEEE 802.3.
+pub const TRUNCATION: u32 = 10;
+
+// Backoff is a policy for how long to wait before sensing the medium again, having failed to
+// transmit a packet. Every policy maintains a contention window that's a function of the number
+// of retries, waiting for a number of slots drawn uniformly from [0, window].
+pub trait Backoff {
+    // Backoff.window returns the contention window, in slots, after the given number of retries
+    // (starting at 1).
+    fn window(&self, retries: u32) -> u32;
+
+    // Backoff.slots draws the number of slots to wait after the given number of retries, short of
+    // u32::MAX slots for a window that large.
+    fn slots(&self, rng: &mut StdRng, retries: u32) -> u32 {
+        rng.gen_range(0, self.window(retries).saturating_add(1))
+    }
+}
+
+// Exponential is truncated binary exponential backoff, the window doubling with every retry up
+// to 2^truncation - 1 slots, or u32::MAX past 31 retries.
+pub struct Exponential {
+    pub truncation: u32,
+}
+
+impl Backoff for Exponential {
+    fn window(&self, retries: u32) -> u32 {
+        2u32.checked_pow(retries.min(self.truncation)).map_or(u32::MAX, |w| w - 1)
+    }
+}
+
+// Linear grows the window by a slot with every retry.
+pub struct Linear;
+
+impl Backoff for Linear {
+    fn window(&self, retries: u32) -> u32 {
+        retries
+    }
+}
+
+// Constant keeps the window fixed regardless of the number of retries.
+pub struct Constant {
+    pub window: u32,
+}
+
+impl Backoff for Constant {
+    fn window(&self, _retries: u32) -> u32 {
+        self.window
+    }
+}
+
+// Fibonacci grows the window along the Fibonacci sequence (1, 2, 3, 5, 8, ..), up to the window
+// reached after `truncation` retries, capped at u32::MAX.
+pub struct Fibonacci {
+    pub truncation: u32,
+}
+
+impl Backoff for Fibonacci {
+    fn window(&self, retries: u32) -> u32 {
+        let (mut a, mut b) = (1u32, 2);
+        for _ in 1..retries.min(self.truncation) {
+            let next = a.saturating_add(b);
+            a = b;
+            b = next;
+        }
+        a
+    }
+}
+
+// Table looks the window up by the number of retries, the last entry applying to every retry
+// past the end of the table.
+pub struct Table {
+    pub windows: Vec<u32>,
+}
+
+impl Backoff for Table {
+    fn window(&self, retries: u32) -> u32 {
+        let idx = (retries.max(1) as usize - 1).min(self.windows.len() - 1);
+        self.windows[idx]
+    }
+}
+
+// BackoffPolicy selects one of the Backoff implementations, as specified on the command line:
+// 'beb', 'linear', 'constant=W', 'fibonacci' or 'table=W1,W2,..', windows being in slots.
+#[derive(Clone, PartialEq, Debug)]
+pub enum BackoffPolicy {
+    Exponential,
+    Linear,
+    Constant(u32),
+    Fibonacci,
+    Table(Vec<u32>),
+}
+
+impl BackoffPolicy {
+    // BackoffPolicy.build returns the Backoff implementing the policy, the exponential and
+    // Fibonacci windows growing for up to `truncation` retries.
+    pub fn build(&self, truncation: u32) -> Box<dyn Backoff> {
+        match *self {
+            BackoffPolicy::Exponential => Box::new(Exponential { truncation }),
+            BackoffPolicy::Linear => Box::new(Linear),
+            BackoffPolicy::Constant(window) => Box::new(Constant { window }),
+            BackoffPolicy::Fibonacci => Box::new(Fibonacci { truncation }),
+            BackoffPolicy::Table(ref windows) => Box::new(Table { windows: windows.clone() }),
+        }
+    }
+}
+
+impl FromStr for BackoffPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "unknown backoff '{}', expected 'beb', 'linear', 'constant=W', 'fibonacci' or \
+                 'table=W1,W2,..'",
+                s
+            )
+        };
+        match s {
+            "beb" => Ok(BackoffPolicy::Exponential),
+            "linear" => Ok(BackoffPolicy::Linear),
+            "fibonacci" => Ok(BackoffPolicy::Fibonacci),
+            _ if s.starts_with("constant=") => {
+                let window = s["constant=".len()..].parse().map_err(|_| invalid())?;
+                Ok(BackoffPolicy::Constant(window))
+            }
+            _ if s.starts_with("table=") => {
+                let windows = s["table=".len()..]
+                    .split(',')
+                    .map(|w| w.trim().parse().map_err(|_| invalid()))
+                    .collect::<Result<Vec<u32>, String>>()?;
+                Ok(BackoffPolicy::Table(windows))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for BackoffPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BackoffPolicy::Exponential => write!(f, "beb"),
+            BackoffPolicy::Linear => write!(f, "linear"),
+            BackoffPolicy::Constant(window) => write!(f, "constant={}", window),
+            BackoffPolicy::Fibonacci => write!(f, "fibonacci"),
+            BackoffPolicy::Table(ref windows) => {
+                let windows: Vec<_> = windows.iter().map(|w| w.to_string()).collect();
+                write!(f, "table={}", windows.join(","))
+            }
+        }
+    }
+}
+
+// Backoff policies serialize as they're specified on the command line.
+impl Serialize for BackoffPolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::{self, Stream};
+
+    #[test]
+    fn windows() {
+        let windows = |backoff: &dyn Backoff| -> Vec<u32> {
+            [1, 2, 3, 4, 10, 11, 16].iter().map(|&r| backoff.window(r)).collect()
+        };
+        assert_eq!(
+            windows(&Exponential { truncation: TRUNCATION }),
+            vec![1, 3, 7, 15, 1023, 1023, 1023]
+        );
+        assert_eq!(windows(&Linear), vec![1, 2, 3, 4, 10, 11, 16]);
+        assert_eq!(windows(&Constant { window: 4 }), vec![4; 7]);
+        assert_eq!(
+            windows(&Fibonacci { truncation: TRUNCATION }),
+            vec![1, 2, 3, 5, 89, 89, 89]
+        );
+        assert_eq!(
+            windows(&Exponential { truncation: 3 }),
+            vec![1, 3, 7, 7, 7, 7, 7]
+        );
+        assert_eq!(
+            windows(&Table { windows: vec![2, 4, 6] }),
+            vec![2, 4, 6, 6, 6, 6, 6]
+        );
+        // Windows too large for a u32 saturate rather than overflow.
+        assert_eq!(Exponential { truncation: 40 }.window(31), (1 << 31) - 1);
+        assert_eq!(Exponential { truncation: 40 }.window(32), u32::MAX);
+        assert_eq!(Fibonacci { truncation: 100 }.window(100), u32::MAX);
+    }
+
+    #[test]
+    fn slots_within_window() {
+        let backoff = Exponential { truncation: TRUNCATION };
+        let mut rng = rng::stream(0, 0, Stream::Mac);
+        let slots: Vec<_> = (0..1000).map(|_| backoff.slots(&mut rng, 2)).collect();
+        assert!(slots.iter().all(|&s| s <= 3));
+        // Both ends of the window are drawn.
+        assert!(slots.contains(&0) && slots.contains(&3));
+        let backoff = Constant { window: u32::MAX };
+        backoff.slots(&mut rng, 1);
+    }
+
+    #[test]
+    fn parse_backoff_policy() {
+        for &spec in &["beb", "linear", "constant=4", "fibonacci", "table=1,2,4"] {
+            let policy: BackoffPolicy = spec.parse().unwrap();
+            assert_eq!(policy.to_string(), spec);
+        }
+        assert_eq!(
+            "table=1, 2".parse(),
+            Ok(BackoffPolicy::Table(vec![1, 2]))
+        );
+        assert!("constant".parse::<BackoffPolicy>().is_err());
+        assert!("table=".parse::<BackoffPolicy>().is_err());
+        assert!("quadratic".parse::<BackoffPolicy>().is_err());
+    }
+}
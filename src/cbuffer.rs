@@ -17,6 +17,13 @@ impl<T: Clone> CircularBuffer<T> {
         self.vec[self.idx].clone()
     }
 
+    // CircularBuffer.read_behind returns the element written the given number of ticks ago, up to
+    // the size of the buffer. CircularBuffer.read() reads as far back as the buffer goes.
+    pub fn read_behind(&self, ticks: usize) -> &T {
+        assert!(ticks > 0 && ticks <= self.vec.len());
+        &self.vec[(self.idx + self.vec.len() - ticks) % self.vec.len()]
+    }
+
     pub fn write(&mut self, t: T) {
         self.vec[self.idx] = t
     }
@@ -45,4 +52,18 @@ mod tests {
         c.tick();
         assert_eq!(c.read(), 1);
     }
+
+    #[test]
+    fn test_cbuffer_read_behind() {
+        let mut c: super::CircularBuffer<u32> = super::CircularBuffer::new(3, 0);
+        for i in 1..4 {
+            c.write(i);
+            c.tick();
+        }
+
+        assert_eq!(*c.read_behind(1), 3);
+        assert_eq!(*c.read_behind(2), 2);
+        assert_eq!(*c.read_behind(3), 1);
+        assert_eq!(*c.read_behind(3), c.read());
+    }
 }
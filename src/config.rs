@@ -0,0 +1,270 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+use toml::Value;
+use engine::Params;
+use generators::Traffic;
+use simulators::{CsmaMode, Destinations};
+use sizes::PacketSize;
+
+// Group is a group of identically configured nodes, overriding the LAN-wide rate, traffic model,
+// packet size, queue size, CSMA mode and destinations of Params for its nodes. Groups are laid out
+// along the bus in the order they're configured in.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Group {
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub traffic: Option<Traffic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub psize: Option<PacketSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qsize: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csma: Option<CsmaMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dest: Option<Destinations>,
+}
+
+impl Group {
+    // Group::new returns a group of count nodes, none of the LAN-wide parameters overridden.
+    pub fn new(count: usize) -> Group {
+        Group {
+            count,
+            rate: None,
+            traffic: None,
+            psize: None,
+            qsize: None,
+            csma: None,
+            dest: None,
+        }
+    }
+
+    // Group.set sets the parameter, named as it is on the command line, to the given value.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+            value.parse::<T>().map_err(
+                |_| format!("invalid value '{}' for {}", value, name),
+            )
+        }
+
+        match name {
+            "count" => self.count = parse(name, value)?,
+            "rate" => self.rate = Some(parse(name, value)?),
+            "traffic" => self.traffic = Some(value.parse()?),
+            "psize" => self.psize = Some(value.parse()?),
+            "qsize" => self.qsize = Some(parse(name, value)?),
+            "csma" => self.csma = Some(parse(name, value)?),
+            "dest" => self.dest = Some(value.parse()?),
+            _ => return Err(format!("unknown node parameter '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut overrides = vec![];
+        if let Some(rate) = self.rate {
+            overrides.push(format!("{} packets/s", rate));
+        }
+        if let Some(ref traffic) = self.traffic {
+            overrides.push(format!("{} traffic", traffic));
+        }
+        if let Some(ref psize) = self.psize {
+            overrides.push(format!("{} bits", psize));
+        }
+        if let Some(qsize) = self.qsize {
+            overrides.push(format!("queue of {}", qsize));
+        }
+        if let Some(csma) = self.csma {
+            overrides.push(format!("CSMA/CD mode {}", csma));
+        }
+        match self.dest {
+            Some(Destinations::Node(node)) => overrides.push(format!("packets to node {}", node)),
+            Some(dest) => overrides.push(format!("{} destinations", dest)),
+            None => {}
+        }
+        if overrides.is_empty() {
+            overrides.push("defaults".to_string());
+        }
+        write!(f, "{} x {}", self.count, overrides.join(", "))
+    }
+}
+
+// config::load applies the configuration file at path to params. The file is TOML, its top-level
+// keys being any of the parameters named as they are on the command line, with every [[node]]
+// table describing a Group of nodes. Given groups, the number of nodes is that across all of
+// them. For e.g. a file server amidst light clients:
+//
+//   lspeed = 10000000
+//   duration = 10
+//
+//   [[node]]
+//   rate = 500
+//   psize = "uniform=4096,12144"
+//   csma = "1"
+//
+//   [[node]]
+//   count = 20
+//   rate = 5
+//   traffic = "onoff=0.1,1.0"
+//   dest = 0
+pub fn load(path: &str, params: &mut Params) -> Result<(), String> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .map_err(|e| format!("unable to read configuration '{}': {}", path, e))?;
+    apply(&contents, params).map_err(|e| format!("invalid configuration '{}': {}", path, e))
+}
+
+// apply applies the TOML configuration to params (see config::load).
+fn apply(contents: &str, params: &mut Params) -> Result<(), String> {
+    let config = contents.parse::<Value>().map_err(|e| e.to_string())?;
+    let config = config.as_table().unwrap();
+    for (name, value) in config {
+        if name == "node" {
+            continue;
+        }
+        params.set(name, &string(name, value)?)?;
+    }
+
+    let groups = match config.get("node") {
+        Some(groups) => groups.as_array().ok_or(
+            "nodes are to be described by [[node]] tables",
+        )?,
+        None => return Ok(()),
+    };
+    params.groups = groups
+        .iter()
+        .map(|group| {
+            let group = group.as_table().ok_or(
+                "nodes are to be described by [[node]] tables",
+            )?;
+            let mut g = Group::new(1);
+            for (name, value) in group {
+                g.set(name, &string(name, value)?)?;
+            }
+            Ok(g)
+        })
+        .collect::<Result<_, String>>()?;
+
+    let count = params.groups.iter().map(|g| g.count).sum();
+    if config.contains_key("ncount") && params.ncount != count {
+        return Err(format!(
+            "ncount is {} but the node groups add up to {} nodes",
+            params.ncount,
+            count
+        ));
+    }
+    params.ncount = count;
+    Ok(())
+}
+
+// string returns the value as it would be specified on the command line.
+fn string(name: &str, value: &Value) -> Result<String, String> {
+    match *value {
+        Value::String(ref s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(format!("invalid value for {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine;
+
+    #[test]
+    fn heterogeneous_lan() {
+        let mut params = Params::default();
+        apply(
+            r#"
+            lspeed = 10000000
+            duration = 2
+            warmup = 0.5
+
+            [[node]]
+            rate = 500
+            psize = "uniform=4096,12144"
+            csma = "1"
+
+            [[node]]
+            count = 20
+            rate = 5
+            traffic = "onoff=0.1,1.0"
+            qsize = 8
+            dest = 0
+            "#,
+            &mut params,
+        ).unwrap();
+
+        assert_eq!((params.lspeed, params.duration, params.warmup), (10_000_000, 2, 0.5));
+        assert_eq!(params.ncount, 21);
+        assert_eq!(params.groups.len(), 2);
+        assert_eq!(params.groups[0].count, 1);
+        assert_eq!(params.groups[0].csma, Some(CsmaMode::OnePersistent));
+        assert_eq!(params.groups[1].qsize, Some(8));
+        assert_eq!(params.groups[1].psize, None);
+        assert_eq!(
+            params.groups[1].to_string(),
+            "20 x 5 packets/s, onoff=0.1,1 traffic, queue of 8, packets to node 0"
+        );
+    }
+
+    #[test]
+    fn invalid_configuration() {
+        for config in &[
+            "lspeed = ",
+            "bandwidth = 10",
+            "rate = \"fast\"",
+            "rate = [10]",
+            "node = 3",
+            "[[node]]\nweight = 2",
+            "[[node]]\ncsma = \"2\"",
+            "[[node]]\ndest = \"anycast\"",
+            "ncount = 3\n[[node]]\ncount = 2",
+        ]
+        {
+            assert!(apply(config, &mut Params::default()).is_err(), "{}", config);
+        }
+        assert!(load("/nonexistent/nsim.toml", &mut Params::default()).is_err());
+    }
+
+    // Every node of a group is configured as per the group, falling back on the LAN-wide
+    // parameters for whatever the group leaves out.
+    #[test]
+    fn heterogeneous_run() {
+        // A heavy 1-persistent file server amidst light p-persistent clients.
+        let mut server = Group::new(1);
+        server.set("rate", "200").unwrap();
+        server.set("psize", "uniform=4000,12000").unwrap();
+        server.set("csma", "1").unwrap();
+        let mut clients = Group::new(4);
+        clients.set("rate", "10").unwrap();
+        clients.set("csma", "p=0.5").unwrap();
+        let params = Params {
+            psize: PacketSize::Constant(1000),
+            qsize: Some(4),
+            ncount: 5,
+            groups: vec![server, clients],
+            duration: 1,
+            resolution: 1e5,
+            seed: 7,
+            ..Params::default()
+        };
+        assert_eq!(params.node(0).rate, 200);
+        assert_eq!(params.node(0).qsize, Some(4));
+        assert_eq!(params.node(4).csma, CsmaMode::PPersistent(0.5));
+        assert_eq!(params.node(4).psize, &params.psize);
+
+        let report = engine::run(&params);
+        let server = &report.statistics[0];
+        assert!(server.packets_generated > 10 * report.statistics[1].packets_generated);
+        assert!(server.bits_processed >= 4000 * u64::from(server.packets_processed));
+    }
+}
@@ -0,0 +1,966 @@
+use std::fmt;
+use std::str::FromStr;
+use bit_vec::BitVec;
+use serde::{Serialize, Serializer};
+use stats::{self, OnlineStats};
+use aloha;
+use backoff::{BackoffPolicy, TRUNCATION};
+use config::Group;
+use log::{self, EventLog, Filter};
+use mac::Mac;
+use token::TOKEN_BITS;
+use generators::Traffic;
+use sizes::PacketSize;
+use simulators::{ChannelStatistics, CsmaMode, Destinations, Medium, Server, ServerStatistics,
+                 Timing, MAX_ATTEMPTS};
+use scheduler::Scheduler;
+use series::{Sample, Sampler};
+use switch::{PortStatistics, Switch};
+use topology::Topology;
+
+// The default simulation parameters.
+pub const DEFAULT_RATE: u32 = 10;
+pub const DEFAULT_TRAFFIC: Traffic = Traffic::Poisson;
+pub const DEFAULT_PSIZE: PacketSize = PacketSize::Constant(1);
+pub const DEFAULT_LSPEED: u32 = 1_000_000;
+pub const DEFAULT_DURATION: u32 = 5;
+pub const DEFAULT_SERVER_COUNT: usize = 10;
+// The default bus, 10 nodes 570m apart, has the 25.6us (26 ticks) end-to-end propagation delay
+// that used to be hardcoded. Nodes keep their spacing whatever their number, the bus growing
+// longer, and slower to propagate across, with every node added.
+pub const DEFAULT_SPACING: f64 = 570.0;
+pub const DEFAULT_SPEED: f64 = 2e8;
+pub const DEFAULT_DEST: Destinations = Destinations::Uniform;
+pub const DEFAULT_LAN: Lan = Lan::Bus;
+pub const DEFAULT_LATENCY: f64 = 0.0;
+pub const DEFAULT_MAC: Mac = Mac::Csma;
+pub const DEFAULT_THT: f64 = 0.01;
+pub const DEFAULT_TLOSS: f64 = 0.0;
+pub const DEFAULT_TREGEN: f64 = 0.01;
+pub const DEFAULT_CSMA: CsmaMode = CsmaMode::NonPersistent;
+pub const DEFAULT_BACKOFF: BackoffPolicy = BackoffPolicy::Exponential;
+pub const DEFAULT_ALOHA_BACKOFF: BackoffPolicy = BackoffPolicy::Constant(aloha::WINDOW);
+pub const DEFAULT_ATTEMPTS: u32 = MAX_ATTEMPTS;
+pub const DEFAULT_TRUNCATION: u32 = TRUNCATION;
+pub const DEFAULT_RESOLUTION: f64 = 1e6;
+pub const DEFAULT_ENGINE: Engine = Engine::Tick;
+pub const DEFAULT_WARMUP: f64 = 0.0;
+pub const DEFAULT_REPLICATIONS: u32 = 1;
+pub const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+// Engine selects how the simulation advances time. Engine::Tick walks every tick of the
+// simulation, ticking every Server and the Medium. Engine::Event only visits the instants at
+// which something happens (see scheduler::Scheduler) and is considerably faster on lightly loaded
+// LANs, simulating the same CSMA/CD semantics.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Engine {
+    Tick,
+    Event,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tick" => Ok(Engine::Tick),
+            "event" => Ok(Engine::Event),
+            _ => Err(format!("unknown engine '{}', expected 'tick' or 'event'", s)),
+        }
+    }
+}
+
+// Engines serialize as they're specified on the command line.
+impl Serialize for Engine {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Engine::Tick => write!(f, "tick"),
+            Engine::Event => write!(f, "event"),
+        }
+    }
+}
+
+// Lan selects how the nodes are interconnected. Lan::Bus has them share a single collision domain
+// (see simulators::Medium), Lan::Switch gives every node a dedicated full-duplex link to a
+// store-and-forward switch (see switch::Switch).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Lan {
+    Bus,
+    Switch,
+}
+
+impl FromStr for Lan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bus" => Ok(Lan::Bus),
+            "switch" => Ok(Lan::Switch),
+            _ => Err(format!("unknown LAN '{}', expected 'bus' or 'switch'", s)),
+        }
+    }
+}
+
+// Lans serialize as they're specified on the command line.
+impl Serialize for Lan {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for Lan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Lan::Bus => write!(f, "bus"),
+            Lan::Switch => write!(f, "switch"),
+        }
+    }
+}
+
+// Params is the full configuration of a simulation run. mac is how nodes on the bus access it,
+// CSMA/CD configured by csma, backoff, attempts and truncation (ALOHA by the latter three, see
+// Params.backoff_policy for the default backoff policy of either), token passing by tht, the token
+// holding time, tlatency, the time it takes to pass the token on to the next node (that of
+// transmitting the token by default), tloss, the probability of losing it on every pass, and
+// tregen, the time it takes to regenerate it (see token::Ring), all but tloss in seconds. A
+// switched LAN buffers at most pbuffer packets per output port (unbounded if None), forwarding
+// packets latency seconds after receiving them. warmup is the initial period, in seconds, whose
+// statistics are discarded. replications is the number of independent replications to run, each
+// differing only in its seed (see replication::replicas), and confidence the level of the
+// confidence intervals estimated across them. sample, if set, is the interval at which the run is
+// sampled, in seconds (see series::Sample). groups, if any, override the rate, traffic model,
+// packet size, queue size, CSMA mode and destinations of their nodes (see Params.node). log, if
+// set, has the run log the events its Filter admits (see Report.log).
+#[derive(Clone, Debug, Serialize)]
+pub struct Params {
+    pub rate: u32,
+    pub psize: PacketSize,
+    pub traffic: Traffic,
+    pub ethernet: bool,
+    pub lspeed: u32,
+    pub duration: u32,
+    pub ncount: usize,
+    pub spacing: f64,
+    pub speed: f64,
+    pub dest: Destinations,
+    pub lan: Lan,
+    pub pbuffer: Option<usize>,
+    pub latency: f64,
+    pub mac: Mac,
+    pub tht: f64,
+    pub tlatency: Option<f64>,
+    pub tloss: f64,
+    pub tregen: f64,
+    pub csma: CsmaMode,
+    pub backoff: Option<BackoffPolicy>,
+    pub attempts: u32,
+    pub truncation: u32,
+    pub qsize: Option<usize>,
+    pub resolution: f64,
+    pub seed: u64,
+    pub engine: Engine,
+    pub warmup: f64,
+    pub replications: u32,
+    pub confidence: f64,
+    pub sample: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group>,
+    #[serde(skip)]
+    pub log: Option<Filter>,
+}
+
+// NodeParams are the parameters of a single node, those of its Group falling back to the
+// LAN-wide Params.
+pub struct NodeParams<'a> {
+    pub rate: u32,
+    pub traffic: &'a Traffic,
+    pub psize: &'a PacketSize,
+    pub qsize: Option<usize>,
+    pub csma: CsmaMode,
+    pub dest: Destinations,
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            rate: DEFAULT_RATE,
+            psize: DEFAULT_PSIZE,
+            traffic: DEFAULT_TRAFFIC,
+            ethernet: false,
+            lspeed: DEFAULT_LSPEED,
+            duration: DEFAULT_DURATION,
+            ncount: DEFAULT_SERVER_COUNT,
+            spacing: DEFAULT_SPACING,
+            speed: DEFAULT_SPEED,
+            dest: DEFAULT_DEST,
+            lan: DEFAULT_LAN,
+            pbuffer: None,
+            latency: DEFAULT_LATENCY,
+            mac: DEFAULT_MAC,
+            tht: DEFAULT_THT,
+            tlatency: None,
+            tloss: DEFAULT_TLOSS,
+            tregen: DEFAULT_TREGEN,
+            csma: DEFAULT_CSMA,
+            backoff: None,
+            attempts: DEFAULT_ATTEMPTS,
+            truncation: DEFAULT_TRUNCATION,
+            qsize: None,
+            resolution: DEFAULT_RESOLUTION,
+            seed: 0,
+            engine: DEFAULT_ENGINE,
+            warmup: DEFAULT_WARMUP,
+            replications: DEFAULT_REPLICATIONS,
+            confidence: DEFAULT_CONFIDENCE,
+            sample: None,
+            groups: vec![],
+            log: None,
+        }
+    }
+}
+
+impl Params {
+    // Params.set sets the parameter, named as it is on the command line, to the given value.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+            value.parse::<T>().map_err(
+                |_| format!("invalid value '{}' for {}", value, name),
+            )
+        }
+
+        match name {
+            "rate" => self.rate = parse(name, value)?,
+            "psize" => self.psize = value.parse()?,
+            "traffic" => self.traffic = value.parse()?,
+            "ethernet" => self.ethernet = parse(name, value)?,
+            "lspeed" => self.lspeed = parse(name, value)?,
+            "duration" => self.duration = parse(name, value)?,
+            "ncount" => self.ncount = parse(name, value)?,
+            "spacing" => self.spacing = parse(name, value)?,
+            "speed" => self.speed = parse(name, value)?,
+            "dest" => self.dest = value.parse()?,
+            "lan" => self.lan = parse(name, value)?,
+            "pbuffer" => self.pbuffer = Some(parse(name, value)?),
+            "latency" => self.latency = parse(name, value)?,
+            "mac" => self.mac = parse(name, value)?,
+            "tht" => self.tht = parse(name, value)?,
+            "tlatency" => self.tlatency = Some(parse(name, value)?),
+            "tloss" => self.tloss = parse(name, value)?,
+            "tregen" => self.tregen = parse(name, value)?,
+            "csma" => self.csma = parse(name, value)?,
+            "backoff" => self.backoff = Some(parse(name, value)?),
+            "attempts" => self.attempts = parse(name, value)?,
+            "truncation" => self.truncation = parse(name, value)?,
+            "qsize" => self.qsize = Some(parse(name, value)?),
+            "resolution" => self.resolution = parse(name, value)?,
+            "seed" => self.seed = parse(name, value)?,
+            "engine" => self.engine = parse(name, value)?,
+            "warmup" => self.warmup = parse(name, value)?,
+            "replications" => self.replications = parse(name, value)?,
+            "confidence" => self.confidence = parse(name, value)?,
+            "sample" => self.sample = Some(parse(name, value)?),
+            _ => return Err(format!("unknown parameter '{}'", name)),
+        }
+        Ok(())
+    }
+
+    // Params.validate checks the parameters are consistent with one another and that the
+    // configured engine can simulate the LAN they describe.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.warmup_ticks() >= self.ticks() {
+            return Err("warm-up period must be shorter than the simulation".to_string());
+        }
+        let groups = self.groups.iter().filter_map(|g| g.traffic.as_ref());
+        for traffic in Some(&self.traffic).into_iter().chain(groups) {
+            if traffic.nodes() > self.ncount {
+                return Err(format!(
+                    "the trace has packets from {} nodes, more than the {} simulated",
+                    traffic.nodes(),
+                    self.ncount
+                ));
+            }
+        }
+        if !self.groups.is_empty() && self.groups.iter().map(|g| g.count).sum::<usize>() !=
+            self.ncount
+        {
+            return Err(format!(
+                "the node groups don't add up to the {} nodes simulated",
+                self.ncount
+            ));
+        }
+        for id in 0..self.ncount {
+            if let Destinations::Node(node) = self.node(id).dest {
+                if node >= self.ncount {
+                    return Err(format!(
+                        "packets are addressed to node {}, out of the {} simulated",
+                        node,
+                        self.ncount
+                    ));
+                }
+            }
+        }
+        if self.lan == Lan::Switch {
+            if self.engine != Engine::Tick || self.sample.is_some() || self.log.is_some() {
+                return Err(
+                    "the switched LAN is only simulated by the tick engine, unsampled and unlogged"
+                        .to_string(),
+                );
+            }
+        } else if self.engine != Engine::Tick && self.mac != Mac::Csma {
+            return Err(format!(
+                "the event engine only simulates CSMA/CD, {} needs the tick engine",
+                self.mac
+            ));
+        }
+        Ok(())
+    }
+
+    // Params.backoff_policy returns the backoff policy nodes retransmit as per, if not set that of
+    // the MAC: truncated binary exponential backoff for CSMA/CD and a constant window for ALOHA,
+    // spreading retransmissions out as evenly as theory has them (see aloha::throughput).
+    pub fn backoff_policy(&self) -> BackoffPolicy {
+        match self.backoff {
+            Some(ref backoff) => backoff.clone(),
+            None if self.mac == Mac::Aloha || self.mac == Mac::SlottedAloha => {
+                DEFAULT_ALOHA_BACKOFF
+            }
+            None => DEFAULT_BACKOFF,
+        }
+    }
+
+    // Params.ticks returns the number of ticks the simulation runs for.
+    pub fn ticks(&self) -> u32 {
+        (f64::from(self.duration) * self.resolution) as u32
+    }
+
+    // Params.warmup_ticks returns the number of ticks whose statistics are discarded.
+    pub fn warmup_ticks(&self) -> u32 {
+        (self.warmup * self.resolution) as u32
+    }
+
+    // Params.timing returns the CSMA/CD timing parameters at the configured resolution.
+    pub fn timing(&self) -> Timing {
+        Timing::new(self.resolution, f64::from(self.lspeed))
+    }
+
+    // Params.topology returns the bus the nodes are attached to.
+    pub fn topology(&self) -> Topology {
+        Topology::bus(self.ncount, self.spacing, self.speed)
+    }
+
+    // Params.node returns the parameters of the node with the given id.
+    pub fn node(&self, id: usize) -> NodeParams<'_> {
+        let mut first = 0;
+        let group = self.groups.iter().find(|g| {
+            first += g.count;
+            id < first
+        });
+        NodeParams {
+            rate: group.and_then(|g| g.rate).unwrap_or(self.rate),
+            traffic: group.and_then(|g| g.traffic.as_ref()).unwrap_or(&self.traffic),
+            psize: group.and_then(|g| g.psize.as_ref()).unwrap_or(&self.psize),
+            qsize: group.and_then(|g| g.qsize).or(self.qsize),
+            csma: group.and_then(|g| g.csma).unwrap_or(self.csma),
+            dest: group.and_then(|g| g.dest).unwrap_or(self.dest),
+        }
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Simulation configuration:").unwrap();
+        writeln!(f, "\t Rate:                  {} packets/s", self.rate).unwrap();
+        if self.ethernet {
+            writeln!(f, "\t Packet size:           {} bits (Ethernet frames)", self.psize)
+        } else {
+            writeln!(f, "\t Packet size:           {} bits", self.psize)
+        }.unwrap();
+        writeln!(f, "\t Traffic:               {}", self.traffic).unwrap();
+        writeln!(f, "\t LAN speed:             {} bits/s", self.lspeed).unwrap();
+        writeln!(f, "\t Simulation duration:   {}s", self.duration).unwrap();
+        writeln!(f, "\t Server count:          {} Clients", self.ncount).unwrap();
+        for group in &self.groups {
+            writeln!(f, "\t Node group:            {}", group).unwrap();
+        }
+        writeln!(f, "\t Node spacing:          {} m", self.spacing).unwrap();
+        writeln!(f, "\t Propagation speed:     {} m/s", self.speed).unwrap();
+        writeln!(f, "\t Destinations:          {}", self.dest).unwrap();
+        match (self.lan, self.pbuffer) {
+            (Lan::Bus, _) => writeln!(f, "\t LAN:                   shared bus"),
+            (Lan::Switch, Some(pbuffer)) => writeln!(
+                f,
+                "\t LAN:                   switched ({} packets/port, {}s latency)",
+                pbuffer,
+                self.latency
+            ),
+            (Lan::Switch, None) => writeln!(
+                f,
+                "\t LAN:                   switched (unbounded ports, {}s latency)",
+                self.latency
+            ),
+        }.unwrap();
+        // Nodes on a switched LAN never contend for their links, there's no MAC to speak of.
+        if self.lan == Lan::Bus {
+            writeln!(f, "\t MAC protocol:          {}", self.mac).unwrap();
+            if self.mac == Mac::Token {
+                let timing = self.timing();
+                let tlatency = self.tlatency.unwrap_or(TOKEN_BITS / timing.lspeed);
+                writeln!(
+                    f,
+                    "\t Token:                 {}s held, {}s passed on, {} lost, {}s regenerated",
+                    self.tht,
+                    timing.seconds(timing.ticks(tlatency)),
+                    self.tloss,
+                    self.tregen
+                ).unwrap();
+            }
+            writeln!(f, "\t CSMA/CD mode:          {}", self.csma).unwrap();
+            writeln!(
+                f,
+                "\t Backoff:               {} ({} attempts, truncated at {} retries)",
+                self.backoff_policy(),
+                self.attempts,
+                self.truncation
+            ).unwrap();
+        }
+        match self.qsize {
+            Some(qsize) => writeln!(f, "\t Queue size:            {} packets", qsize),
+            None => writeln!(f, "\t Queue size:            unbounded"),
+        }.unwrap();
+        writeln!(
+            f,
+            "\t Resolution:            {}s ({} ticks/s)",
+            1.0 / self.resolution,
+            self.resolution
+        ).unwrap();
+        writeln!(f, "\t Seed:                  {}", self.seed).unwrap();
+        writeln!(f, "\t Engine:                {}", self.engine).unwrap();
+        writeln!(f, "\t Warm-up period:        {}s", self.warmup).unwrap();
+        writeln!(
+            f,
+            "\t Replications:          {} ({}% confidence)",
+            self.replications,
+            self.confidence * 100.0
+        ).unwrap();
+        if let Some(sample) = self.sample {
+            writeln!(f, "\t Sampling interval:     {}s", sample).unwrap();
+        }
+        let timing = self.timing();
+        let topology = self.topology();
+        writeln!(
+            f,
+            "\t Ticks per packet:      {}",
+            self.psize.mean() / f64::from(self.lspeed) * self.resolution
+        ).unwrap();
+        writeln!(
+            f,
+            "\t Sensing/slot/jam:      {}/{}/{} ticks",
+            timing.sensing,
+            timing.slot,
+            timing.jam
+        ).unwrap();
+        write!(
+            f,
+            "\t End-to-end delay:      {} ticks ({} m)",
+            timing.ticks(topology.length() / self.speed),
+            topology.length()
+        )
+    }
+}
+
+// Report is the outcome of a simulation run: the sojourn time of every processed packet, in
+// seconds, the statistics of each Server and those of the medium. duration is the simulated time,
+// in seconds, and lspeed the LAN speed, in bits/s. series holds the samples of the run if
+// Params.sample is set and log the events logged over it if Params.log is, both including the
+// warm-up period. For a switched LAN, ports holds the statistics of every output port of the
+// switch, indexed by the node it's linked to, while those of the medium count the ticks of every
+// link from a node to the switch, none ever seeing a collision.
+pub struct Report {
+    pub sojourn: OnlineStats,
+    pub statistics: Vec<ServerStatistics>,
+    pub channel: ChannelStatistics,
+    pub duration: f64,
+    pub lspeed: f64,
+    pub series: Vec<Sample>,
+    pub log: Vec<log::Entry>,
+    pub ports: Vec<PortStatistics>,
+}
+
+impl Report {
+    // Report.packets_generated returns the number of packets generated across all Servers.
+    pub fn packets_generated(&self) -> u32 {
+        self.statistics.iter().map(|s| s.packets_generated).sum()
+    }
+
+    // Report.packets_received returns the number of packets received across all Servers, a
+    // broadcast counting once per receiver.
+    pub fn packets_received(&self) -> u32 {
+        self.statistics.iter().map(|s| s.packets_received).sum()
+    }
+
+    // Report.delay returns the end-to-end delay of every packet received, in seconds.
+    pub fn delay(&self) -> OnlineStats {
+        stats::merge_all(self.statistics.iter().map(|s| s.delay)).unwrap_or_default()
+    }
+
+    // Report.packets_port_dropped returns the number of packets dropped by the switch on arrival
+    // to a full output port.
+    pub fn packets_port_dropped(&self) -> u32 {
+        self.ports.iter().map(|p| p.packets_port_dropped).sum()
+    }
+
+    // Report.offered_load returns the number of transmission attempts, successful or colliding,
+    // per packet transmission time (the offered load G, against which the efficiency is the
+    // throughput S), packets taking as long to transmit as those processed on average. It's 0 if
+    // no packet was processed.
+    pub fn offered_load(&self) -> f64 {
+        if self.packets_processed() == 0 {
+            return 0.0;
+        }
+        let attempts = f64::from(self.packets_processed() + self.collisions());
+        let length = self.bits_processed() as f64 / f64::from(self.packets_processed());
+        attempts * length / self.lspeed / self.duration
+    }
+
+    // Report.packets_processed returns the number of packets processed across all Servers.
+    pub fn packets_processed(&self) -> u32 {
+        self.statistics.iter().map(|s| s.packets_processed).sum()
+    }
+
+    // Report.packets_dropped returns the number of packets dropped across all Servers after
+    // exhausting their retries.
+    pub fn packets_dropped(&self) -> u32 {
+        self.statistics.iter().map(|s| s.packets_dropped).sum()
+    }
+
+    // Report.packets_tail_dropped returns the number of packets dropped across all Servers on
+    // arrival to a full queue.
+    pub fn packets_tail_dropped(&self) -> u32 {
+        self.statistics.iter().map(|s| s.packets_tail_dropped).sum()
+    }
+
+    // Report.bits_processed returns the number of bits successfully transmitted across all
+    // Servers.
+    pub fn bits_processed(&self) -> u64 {
+        self.statistics.iter().map(|s| s.bits_processed).sum()
+    }
+
+    // Report.collisions returns the number of collisions detected across all Servers.
+    pub fn collisions(&self) -> u32 {
+        self.statistics.iter().map(|s| s.collisions).sum()
+    }
+
+    // Report.throughput returns the number of bits successfully transmitted per second.
+    pub fn throughput(&self) -> f64 {
+        self.bits_processed() as f64 / self.duration
+    }
+
+    // Report.efficiency returns the throughput as a fraction of the LAN speed.
+    pub fn efficiency(&self) -> f64 {
+        self.throughput() / self.lspeed
+    }
+
+    // Report.node_throughput returns the number of bits successfully transmitted per second by
+    // the given Server.
+    pub fn node_throughput(&self, id: usize) -> f64 {
+        self.statistics[id].bits_processed as f64 / self.duration
+    }
+
+    // Report.share returns the fraction of the throughput due to the given Server, 0 if nothing
+    // was transmitted.
+    pub fn share(&self, id: usize) -> f64 {
+        if self.bits_processed() == 0 {
+            return 0.0;
+        }
+        self.statistics[id].bits_processed as f64 / self.bits_processed() as f64
+    }
+
+    // Report.fairness returns Jain's fairness index over the throughput of every Server, ranging
+    // from 1/n when a single Server gets all of it to 1 when all get an equal share, as they do
+    // when none gets any.
+    pub fn fairness(&self) -> f64 {
+        let throughputs: Vec<_> = (0..self.statistics.len())
+            .map(|id| self.node_throughput(id))
+            .collect();
+        let sum: f64 = throughputs.iter().sum();
+        let squares: f64 = throughputs.iter().map(|x| x * x).sum();
+        if squares == 0.0 {
+            return 1.0;
+        }
+        sum * sum / (throughputs.len() as f64 * squares)
+    }
+}
+
+// run simulates the LAN described by params using the configured Engine, the params having been
+// validated (see Params.validate).
+pub fn run(params: &Params) -> Report {
+    params.validate().unwrap();
+    if params.lan == Lan::Switch {
+        return Switch::new(params).run();
+    }
+    match params.engine {
+        Engine::Tick => tick(params),
+        Engine::Event => Scheduler::new(params).run(),
+    }
+}
+
+fn tick(params: &Params) -> Report {
+    let timing = params.timing();
+    let mut macs = params.mac.build(params).into_iter();
+    let mut servers: Vec<_> = (0..params.ncount)
+        .map(|id| {
+            let node = params.node(id);
+            let server = Server::new(
+                id,
+                node.traffic.build(f64::from(node.rate), id),
+                node.psize.build(params.ethernet),
+                timing,
+                node.csma,
+                node.qsize,
+                params.seed,
+            ).with_destinations(params.ncount, node.dest)
+                .with_mac(macs.next().unwrap());
+            match params.log {
+                Some(ref filter) => server.with_log(EventLog::new(filter, params.resolution)),
+                None => server,
+            }
+        })
+        .collect();
+
+    let mut sojourn = OnlineStats::new();
+    let mut medium = Medium::with_delays(params.topology().delays(&timing));
+    if let Some(ref filter) = params.log {
+        medium = medium.with_log(EventLog::new(filter, params.resolution));
+    }
+    let warmup = params.warmup_ticks();
+    let mut sampler = params.sample.map(
+        |interval| Sampler::new(interval, params.ticks(), timing),
+    );
+
+    for i in 0..params.ticks() {
+        if i == warmup {
+            for server in servers.iter_mut() {
+                server.reset_statistics();
+            }
+            medium.reset_statistics();
+            sojourn = OnlineStats::new();
+        }
+        // TODO(irfansharif): Look at and try to use smart pointers, share link ownership with
+        // Clients and the Server such that the main loop body simply ticks all participants
+        // instead of additionally shuffling data around.
+        if let Some(ref mut sampler) = sampler {
+            if sampler.next() == Some(i) {
+                sampler.queues(servers.iter().map(|s| s.queue_len()).collect());
+            }
+        }
+        let mut local_state = BitVec::from_elem(params.ncount, false);
+        // TODO: Be able to handle multiple packet output
+        // With a packet length of 1000, its impossible for more than 1 packet to be outputted at a
+        // given tick
+        for server in servers.iter_mut() {
+            let collisions = server.statistics().collisions;
+            let packet = server.tick(&mut local_state, &medium, i);
+            if let Some(p) = packet {
+                sojourn.add(timing.seconds(i - p.time_generated));
+                medium.transmitted(p);
+            }
+            if let Some(ref mut sampler) = sampler {
+                if let Some(p) = packet {
+                    sampler.processed(i, p.length);
+                }
+                for _ in collisions..server.statistics().collisions {
+                    sampler.collision(i);
+                }
+            }
+        }
+        if let Some(ref mut sampler) = sampler {
+            if local_state.any() {
+                sampler.busy(i, i + 1);
+            }
+        }
+        let jammers = servers.iter().filter(|s| s.is_jamming()).count();
+        medium.write_with_jam(local_state, jammers);
+        medium.tick();
+    }
+
+    let series = sampler.map(|mut sampler| {
+        while sampler.next().is_some() {
+            sampler.queues(servers.iter().map(|s| s.queue_len()).collect());
+        }
+        sampler.samples()
+    });
+    let logs = medium
+        .take_log()
+        .into_iter()
+        .chain(servers.iter_mut().filter_map(|s| s.take_log()))
+        .collect();
+    Report {
+        sojourn,
+        statistics: servers.iter().map(|s| s.statistics().clone()).collect(),
+        channel: medium.statistics(),
+        duration: timing.seconds(params.ticks() - warmup),
+        lspeed: timing.lspeed,
+        series: series.unwrap_or_default(),
+        log: log::merge(logs),
+        ports: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use trace::Record;
+
+    // params describes a contended LAN simulated at a coarse resolution, packets taking 100 ticks
+    // to transmit.
+    fn params(seed: u64, engine: Engine) -> Params {
+        Params {
+            rate: 40,
+            psize: PacketSize::Constant(1000),
+            duration: 1,
+            spacing: 500.0,
+            qsize: Some(4),
+            resolution: 1e5,
+            seed,
+            engine,
+            ..Params::default()
+        }
+    }
+
+    #[test]
+    fn set_params() {
+        let mut p = params(0, Engine::Tick);
+        p.set("rate", "20").unwrap();
+        p.set("qsize", "8").unwrap();
+        p.set("engine", "event").unwrap();
+        p.set("traffic", "pareto=1.5").unwrap();
+        assert_eq!(p.traffic, Traffic::Pareto(1.5));
+        p.set("psize", "uniform=512,1024").unwrap();
+        p.set("lan", "switch").unwrap();
+        p.set("pbuffer", "16").unwrap();
+        p.set("mac", "token").unwrap();
+        p.set("tlatency", "0.001").unwrap();
+        p.set("tloss", "0.1").unwrap();
+        assert_eq!((p.lan, p.pbuffer), (Lan::Switch, Some(16)));
+        assert_eq!((p.mac, p.tlatency, p.tloss), (Mac::Token, Some(0.001), 0.1));
+        assert_eq!(p.backoff_policy(), DEFAULT_BACKOFF);
+        assert_eq!(Params { mac: Mac::Aloha, ..p.clone() }.backoff_policy(), DEFAULT_ALOHA_BACKOFF);
+        p.set("backoff", "linear").unwrap();
+        assert_eq!(Params { mac: Mac::Aloha, ..p.clone() }.backoff_policy(), BackoffPolicy::Linear);
+        assert_eq!(p.psize, PacketSize::Uniform { min: 512, max: 1024 });
+        assert_eq!((p.rate, p.qsize, p.engine), (20, Some(8), Engine::Event));
+
+        assert!(p.set("rate", "fast").is_err());
+        assert!(p.set("mac", "polling").is_err());
+        assert!(p.set("bogus", "1").is_err());
+    }
+
+    // Combinations the engines can't simulate are rejected upfront, rather than by the run.
+    #[test]
+    fn validate_params() {
+        let p = params(0, Engine::Tick);
+        assert!(p.validate().is_ok());
+        // A trace with packets from node 10, out of the 10 simulated, LAN-wide or for a group.
+        let trace = Traffic::Trace {
+            path: "trace.csv".to_string(),
+            records: vec![Record { time: 0.0, node: 10, length: 1000 }],
+        };
+        let mut group = Group::new(p.ncount);
+        group.traffic = Some(trace.clone());
+        let invalid = [
+            Params { warmup: 1.0, ..p.clone() },
+            Params { traffic: trace, ..p.clone() },
+            Params { groups: vec![group], ..p.clone() },
+            Params { dest: Destinations::Node(10), ..p.clone() },
+            Params { mac: Mac::Aloha, engine: Engine::Event, ..p.clone() },
+            Params { lan: Lan::Switch, engine: Engine::Event, ..p.clone() },
+            Params { lan: Lan::Switch, sample: Some(0.1), ..p.clone() },
+        ];
+        for p in &invalid {
+            assert!(p.validate().is_err());
+        }
+        assert!(Params { mac: Mac::Token, ..p.clone() }.validate().is_ok());
+    }
+
+    // Statistics gathered during the warm-up period are discarded, the rest of the run is
+    // unaffected.
+    #[test]
+    fn warmup() {
+        for &engine in &[Engine::Tick, Engine::Event] {
+            let cold = run(&params(1, engine));
+            let warm = run(&Params {
+                warmup: 0.5,
+                ..params(1, engine)
+            });
+            assert_eq!(warm.duration, 0.5);
+            assert_eq!(warm.channel.ticks(), cold.channel.ticks() / 2);
+            assert!(warm.packets_generated() < cold.packets_generated());
+            assert!(warm.packets_generated() > cold.packets_generated() / 3);
+            assert!(warm.channel.busy_ticks < cold.channel.busy_ticks);
+        }
+    }
+
+    // The engines draw from the same RNG streams in the same order and so agree exactly, on the
+    // statistics, time series and event log of every LAN they both simulate.
+    #[test]
+    fn engines_agree() {
+        // Nodes 0 and 2 replay 200 packets between them, node 1 stays silent.
+        let records: Vec<_> = (0..200u32)
+            .map(|i| {
+                Record {
+                    time: f64::from(i) * 0.004,
+                    node: (i as usize % 2) * 2,
+                    length: 1000 + i,
+                }
+            })
+            .collect();
+        // A heavy 1-persistent file server amidst light p-persistent clients.
+        let mut server = Group::new(1);
+        server.set("rate", "200").unwrap();
+        server.set("psize", "uniform=4000,12000").unwrap();
+        server.set("csma", "1").unwrap();
+        let mut clients = Group::new(4);
+        clients.set("rate", "10").unwrap();
+        clients.set("csma", "p=0.5").unwrap();
+        // With few attempts and a small window, packets get dropped under heavy load.
+        let limited = Params {
+            rate: 200,
+            csma: CsmaMode::PPersistent(0.5),
+            attempts: 3,
+            truncation: 2,
+            ..params(3, Engine::Tick)
+        };
+        let mut filter = Filter::default();
+        filter.set("nodes", "2").unwrap();
+        filter.set("window", "0.25..0.5").unwrap();
+        let addressed = |dest| {
+            Params {
+                dest,
+                ncount: 4,
+                spacing: 2000.0,
+                ..params(10, Engine::Tick)
+            }
+        };
+
+        let mut lans: Vec<_> = (0..4).map(|seed| params(seed, Engine::Tick)).collect();
+        lans.extend(vec![
+            Params { csma: CsmaMode::OnePersistent, ..params(2, Engine::Tick) },
+            Params { csma: CsmaMode::PPersistent(0.2), ..params(2, Engine::Tick) },
+            limited.clone(),
+            Params { log: Some(Filter::default()), ..limited.clone() },
+            Params { log: Some(filter), ..limited.clone() },
+            // Windows too large for a u32 saturate, nodes backing off for the rest of the run.
+            Params { truncation: 40, attempts: 40, ..limited.clone() },
+            Params { backoff: Some(BackoffPolicy::Constant(u32::MAX)), ..limited },
+            Params { traffic: "onoff=0.01,0.03,1.5".parse().unwrap(), ..params(4, Engine::Tick) },
+            Params { traffic: "mmpp=5,0.02,0.02".parse().unwrap(), ..params(4, Engine::Tick) },
+            Params {
+                psize: "exponential=1000".parse().unwrap(),
+                ethernet: true,
+                ..params(5, Engine::Tick)
+            },
+            Params {
+                traffic: Traffic::Trace {
+                    path: "trace.csv".to_string(),
+                    records,
+                },
+                ncount: 3,
+                ..params(6, Engine::Tick)
+            },
+            Params {
+                ncount: 5,
+                groups: vec![server, clients],
+                ..params(7, Engine::Tick)
+            },
+            Params {
+                rate: 200,
+                warmup: 0.2,
+                sample: Some(0.1),
+                ..params(9, Engine::Tick)
+            },
+            addressed(Destinations::Uniform),
+            addressed(Destinations::Broadcast),
+            addressed(Destinations::Node(1)),
+        ]);
+        for (i, lan) in lans.iter().enumerate() {
+            let tick = run(lan);
+            let event = run(&Params { engine: Engine::Event, ..lan.clone() });
+            assert!(tick.packets_processed() > 0, "LAN {}", i);
+            assert_eq!(tick.packets_generated(), event.packets_generated(), "LAN {}", i);
+            assert_eq!(tick.packets_processed(), event.packets_processed(), "LAN {}", i);
+            assert_eq!(tick.packets_dropped(), event.packets_dropped(), "LAN {}", i);
+            assert_eq!(tick.packets_tail_dropped(), event.packets_tail_dropped(), "LAN {}", i);
+            assert_eq!(tick.bits_processed(), event.bits_processed(), "LAN {}", i);
+            assert_eq!(tick.collisions(), event.collisions(), "LAN {}", i);
+            assert_eq!(tick.sojourn.mean(), event.sojourn.mean(), "LAN {}", i);
+            assert_eq!(tick.channel.ticks(), event.channel.ticks(), "LAN {}", i);
+            assert_eq!(tick.channel.busy_ticks, event.channel.busy_ticks, "LAN {}", i);
+            for (t, e) in tick.statistics.iter().zip(&event.statistics) {
+                assert_eq!(t.packets_received, e.packets_received, "LAN {}", i);
+                assert_eq!(t.bits_received, e.bits_received, "LAN {}", i);
+                assert_eq!(t.delay.mean(), e.delay.mean(), "LAN {}", i);
+            }
+            assert_eq!(tick.series, event.series, "LAN {}", i);
+            assert_eq!(tick.log, event.log, "LAN {}", i);
+        }
+    }
+
+    // report returns the Report of a 2 second run at 1000 bits/s in which every Server
+    // transmitted the given number of bits.
+    fn report(bits: &[u64]) -> Report {
+        Report {
+            sojourn: OnlineStats::new(),
+            statistics: bits.iter()
+                .map(|&bits_processed| {
+                    ServerStatistics {
+                        bits_processed,
+                        ..ServerStatistics::new()
+                    }
+                })
+                .collect(),
+            channel: ChannelStatistics::new(),
+            duration: 2.0,
+            lspeed: 1000.0,
+            series: vec![],
+            log: vec![],
+            ports: vec![],
+        }
+    }
+
+    #[test]
+    fn offered_load() {
+        // Nothing was processed, colliding as the nodes might have.
+        let mut idle = report(&[0, 0]);
+        idle.statistics[0].collisions = 3;
+        assert_eq!(idle.offered_load(), 0.0);
+
+        // 2 packets of 500 bits, one of which collided once: 3 attempts at 0.5s each over 2s.
+        let mut busy = report(&[1000, 0]);
+        busy.statistics[0].packets_processed = 2;
+        busy.statistics[0].collisions = 1;
+        assert_eq!(busy.offered_load(), 0.75);
+    }
+
+    #[test]
+    fn fairness_index() {
+        let even = report(&[100, 100, 100, 100]);
+        assert_eq!(even.fairness(), 1.0);
+        assert_eq!(even.node_throughput(0), 50.0);
+        assert_eq!(even.share(0), 0.25);
+
+        let captured = report(&[400, 0, 0, 0]);
+        assert_eq!(captured.fairness(), 0.25);
+        assert_eq!(captured.share(0), 1.0);
+
+        // No node got anything, equally so.
+        let idle = report(&[0, 0, 0, 0]);
+        assert_eq!(idle.fairness(), 1.0);
+        assert_eq!(idle.share(0), 0.0);
+    }
+}
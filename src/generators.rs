@@ -1,9 +1,15 @@
+use std::fmt;
+use std::str::FromStr;
 use rand::distributions::{Exp, IndependentSample};
-use rand::thread_rng;
+use rand::{Rng, StdRng};
+use serde::{Serialize, Serializer};
+use trace::{self, Record};
 
 // Generators generate events, the generation of which is dictated by which specific Generator is
 // used. The underlying RNG distribution, if configured (consider λ in an exponentially distributed
-// generator for e.g.), should map to an events/s parameter.
+// generator for e.g.), should map to an events/s parameter. Generators hold no randomness of their
+// own, the caller passes in the RNG to draw from so identically seeded simulations are identical.
+// They may however hold state, bursty generators keeping track of the period they're in.
 pub trait Generator {
     // next_event returns an u32 integer corresponding to how many discrete time units of the
     // specified resolution (1e6 for a µs scale for e.g.) would need to pass until
@@ -14,7 +20,24 @@ pub trait Generator {
     // rounding up errors. If the next event was to occur after 5ms, a specified resolution of a 1s
     // scale (asking for the next second the event would occur) would return 0 -- hardly useful
     // information.
-    fn next_event(&self, resolution: f64) -> u32;
+    fn next_event(&mut self, rng: &mut StdRng, resolution: f64) -> u32;
+
+    // packet_length returns the length of the packet generated by the upcoming event, if the
+    // Generator dictates it (as is the case when replaying a trace). Otherwise the Client draws
+    // it from its packet size distribution.
+    fn packet_length(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl<G: Generator + ?Sized> Generator for Box<G> {
+    fn next_event(&mut self, rng: &mut StdRng, resolution: f64) -> u32 {
+        (**self).next_event(rng, resolution)
+    }
+
+    fn packet_length(&self) -> Option<u32> {
+        (**self).packet_length()
+    }
 }
 
 // generators::Markov generates events where the interarrival time between subsequent events is
@@ -30,8 +53,8 @@ impl Markov {
 }
 
 impl Generator for Markov {
-    fn next_event(&self, resolution: f64) -> u32 {
-        (self.exp.ind_sample(&mut thread_rng()) * resolution) as u32
+    fn next_event(&mut self, rng: &mut StdRng, resolution: f64) -> u32 {
+        (self.exp.ind_sample(rng) * resolution) as u32
     }
 }
 
@@ -41,41 +64,488 @@ pub struct Deterministic {
 
 impl Deterministic {
     pub fn new(rate: f64) -> Self {
-        Deterministic { rate: rate }
+        Deterministic { rate }
     }
 }
 
 impl Generator for Deterministic {
-    fn next_event(&self, resolution: f64) -> u32 {
+    fn next_event(&mut self, _rng: &mut StdRng, resolution: f64) -> u32 {
         (resolution / self.rate) as u32
     }
 }
 
+// generators::Pareto generates events where the interarrival time between subsequent events is
+// Pareto distributed with the given shape (> 1), the scale being picked for the mean interarrival
+// time to be 1/rate. The heavy tail makes for occasional long silences in between bursts.
+pub struct Pareto {
+    period: Period,
+}
+
+impl Pareto {
+    pub fn new(rate: f64, shape: f64) -> Self {
+        Pareto { period: Period::pareto(1.0 / rate, shape) }
+    }
+}
+
+impl Generator for Pareto {
+    fn next_event(&mut self, rng: &mut StdRng, resolution: f64) -> u32 {
+        (self.period.sample(rng) * resolution) as u32
+    }
+}
+
+// Period is the distribution of the length, in seconds, of a Modulated generator's periods.
+#[derive(Clone, Copy, Debug)]
+enum Period {
+    Exponential(f64),
+    Pareto { scale: f64, shape: f64 },
+}
+
+impl Period {
+    // Period::pareto returns the Pareto distribution with the given mean and shape.
+    fn pareto(mean: f64, shape: f64) -> Period {
+        Period::Pareto {
+            scale: mean * (shape - 1.0) / shape,
+            shape,
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        match *self {
+            Period::Exponential(mean) => mean,
+            Period::Pareto { scale, shape } => shape * scale / (shape - 1.0),
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        match *self {
+            Period::Exponential(mean) => Exp::new(1.0 / mean).ind_sample(rng),
+            // Inverse transform sampling, 1 - U lying in (0, 1].
+            Period::Pareto { scale, shape } => scale / (1.0 - rng.gen::<f64>()).powf(1.0 / shape),
+        }
+    }
+}
+
+// generators::Modulated alternates between two states, generating Poisson distributed events at
+// a different rate in each and staying in either for a period of random length. An ON/OFF source
+// generates no events in its OFF state, a Markov-modulated Poisson process (MMPP) has
+// exponentially distributed periods. The initial state is drawn in proportion to the mean period
+// lengths, as is the case in the long run.
+pub struct Modulated {
+    rates: [f64; 2],
+    periods: [Period; 2],
+    state: usize,
+    // The time left in the current state, in seconds, None until the first event is drawn.
+    left: Option<f64>,
+}
+
+impl Modulated {
+    // Modulated::on_off returns an ON/OFF source averaging the given rate, with ON and OFF
+    // periods of the given mean lengths. Periods are exponentially distributed or, given a shape,
+    // Pareto distributed.
+    pub fn on_off(rate: f64, on: f64, off: f64, shape: Option<f64>) -> Self {
+        let period = |mean| match shape {
+            Some(shape) => Period::pareto(mean, shape),
+            None => Period::Exponential(mean),
+        };
+        Modulated::new(
+            [rate * (on + off) / on, 0.0],
+            [period(on), period(off)],
+        )
+    }
+
+    // Modulated::mmpp returns a two-state MMPP averaging the given rate, the rate in its high
+    // state being ratio times that in its low state, with mean periods of the given lengths.
+    pub fn mmpp(rate: f64, ratio: f64, high: f64, low: f64) -> Self {
+        let high_rate = rate * (high + low) / (high + low / ratio);
+        Modulated::new(
+            [high_rate, high_rate / ratio],
+            [Period::Exponential(high), Period::Exponential(low)],
+        )
+    }
+
+    fn new(rates: [f64; 2], periods: [Period; 2]) -> Self {
+        Modulated {
+            rates,
+            periods,
+            state: 0,
+            left: None,
+        }
+    }
+}
+
+impl Generator for Modulated {
+    fn next_event(&mut self, rng: &mut StdRng, resolution: f64) -> u32 {
+        let mut left = match self.left {
+            Some(left) => left,
+            None => {
+                let (first, second) = (self.periods[0].mean(), self.periods[1].mean());
+                self.state = if rng.gen::<f64>() < first / (first + second) { 0 } else { 1 };
+                self.periods[self.state].sample(rng)
+            }
+        };
+
+        // Events are memoryless within a state, so having run out the current state without an
+        // event the next one is drawn afresh in the following state.
+        let mut delta = 0.0;
+        loop {
+            let rate = self.rates[self.state];
+            if rate > 0.0 {
+                let next = Exp::new(rate).ind_sample(rng);
+                if next <= left {
+                    self.left = Some(left - next);
+                    return ((delta + next) * resolution) as u32;
+                }
+            }
+            delta += left;
+            self.state = 1 - self.state;
+            left = self.periods[self.state].sample(rng);
+        }
+    }
+}
+
+// generators::Trace replays the packets of a single node from a trace, generating them at the
+// tick they were captured in. As a Client generates at most a packet per tick, packets captured
+// in the same tick are generated in the ticks that follow. Once the trace runs out no more events
+// are generated.
+pub struct Trace {
+    records: Vec<Record>,
+    next: usize,
+    // The tick the last event was generated at.
+    last: Option<u32>,
+}
+
+impl Trace {
+    // Trace::new returns the Trace replaying the given node's packets out of the records.
+    pub fn new(records: &[Record], node: usize) -> Self {
+        Trace {
+            records: records.iter().filter(|r| r.node == node).cloned().collect(),
+            next: 0,
+            last: None,
+        }
+    }
+}
+
+impl Generator for Trace {
+    fn next_event(&mut self, _rng: &mut StdRng, resolution: f64) -> u32 {
+        if self.next == self.records.len() {
+            return u32::MAX;
+        }
+        let tick = (self.records[self.next].time * resolution) as u32;
+        self.next += 1;
+        // The Client generates its first packet at the tick it's first ticked if the event is
+        // 0 ticks away, and every subsequent one at least a tick after the previous.
+        let (tick, delta) = match self.last {
+            None => (tick, tick.saturating_add(1)),
+            Some(last) => {
+                let tick = tick.max(last + 1);
+                (tick, tick - last)
+            }
+        };
+        self.last = Some(tick);
+        delta
+    }
+
+    fn packet_length(&self) -> Option<u32> {
+        // The upcoming event is the last one returned by next_event.
+        self.next.checked_sub(1).map(|i| self.records[i].length)
+    }
+}
+
+// Traffic selects the traffic model of every Client, as specified on the command line:
+// 'poisson', 'deterministic', 'onoff=ON,OFF' with exponentially distributed ON and OFF periods of
+// the given mean lengths (in seconds), 'onoff=ON,OFF,SHAPE' with Pareto distributed ones instead,
+// 'mmpp=RATIO,HIGH,LOW' for a two-state MMPP, 'pareto=SHAPE' for Pareto distributed
+// interarrival times, or 'trace=FILE' to replay a trace (see trace::load). Every model but the
+// trace averages the configured rate, the trace dictating packet lengths too.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Traffic {
+    Poisson,
+    Deterministic,
+    OnOff {
+        on: f64,
+        off: f64,
+        shape: Option<f64>,
+    },
+    Mmpp { ratio: f64, high: f64, low: f64 },
+    Pareto(f64),
+    Trace {
+        path: String,
+        records: Vec<Record>,
+    },
+}
+
+impl Traffic {
+    // Traffic.build returns the Generator implementing the traffic model at the given rate for the
+    // given node.
+    pub fn build(&self, rate: f64, node: usize) -> Box<dyn Generator> {
+        match *self {
+            Traffic::Poisson => Box::new(Markov::new(rate)),
+            Traffic::Deterministic => Box::new(Deterministic::new(rate)),
+            Traffic::OnOff { on, off, shape } => Box::new(Modulated::on_off(rate, on, off, shape)),
+            Traffic::Mmpp { ratio, high, low } => Box::new(Modulated::mmpp(rate, ratio, high, low)),
+            Traffic::Pareto(shape) => Box::new(Pareto::new(rate, shape)),
+            Traffic::Trace { ref records, .. } => Box::new(Trace::new(records, node)),
+        }
+    }
+
+    // Traffic.nodes returns the number of nodes the traffic model requires, that of nodes in the
+    // trace if replaying one.
+    pub fn nodes(&self) -> usize {
+        match *self {
+            Traffic::Trace { ref records, .. } => {
+                records.iter().map(|r| r.node + 1).max().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+}
+
+impl FromStr for Traffic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "unknown traffic model '{}', expected 'poisson', 'deterministic', \
+                 'onoff=ON,OFF[,SHAPE]', 'mmpp=RATIO,HIGH,LOW', 'pareto=SHAPE' or 'trace=FILE' \
+                 with positive periods, RATIO >= 1 and SHAPE > 1",
+                s
+            )
+        };
+        let args = |prefix: &str| -> Result<Vec<f64>, String> {
+            s[prefix.len()..]
+                .split(',')
+                .map(|a| a.trim().parse::<f64>().map_err(|_| invalid()))
+                .collect()
+        };
+        if let Some(path) = s.strip_prefix("trace=") {
+            return Ok(Traffic::Trace {
+                path: path.to_string(),
+                records: trace::load(path)?,
+            });
+        }
+        let traffic = match s {
+            "poisson" => Traffic::Poisson,
+            "deterministic" => Traffic::Deterministic,
+            _ if s.starts_with("onoff=") => {
+                match *args("onoff=")?.as_slice() {
+                    [on, off] => Traffic::OnOff {
+                        on,
+                        off,
+                        shape: None,
+                    },
+                    [on, off, shape] => Traffic::OnOff {
+                        on,
+                        off,
+                        shape: Some(shape),
+                    },
+                    _ => return Err(invalid()),
+                }
+            }
+            _ if s.starts_with("mmpp=") => {
+                match *args("mmpp=")?.as_slice() {
+                    [ratio, high, low] => Traffic::Mmpp { ratio, high, low },
+                    _ => return Err(invalid()),
+                }
+            }
+            _ if s.starts_with("pareto=") => {
+                match *args("pareto=")?.as_slice() {
+                    [shape] => Traffic::Pareto(shape),
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(invalid()),
+        };
+
+        let valid = match traffic {
+            Traffic::Poisson | Traffic::Deterministic | Traffic::Trace { .. } => true,
+            Traffic::OnOff { on, off, shape } => {
+                on > 0.0 && off > 0.0 && shape.is_none_or(|shape| shape > 1.0)
+            }
+            Traffic::Mmpp { ratio, high, low } => ratio >= 1.0 && high > 0.0 && low > 0.0,
+            Traffic::Pareto(shape) => shape > 1.0,
+        };
+        if valid { Ok(traffic) } else { Err(invalid()) }
+    }
+}
+
+impl fmt::Display for Traffic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Traffic::Poisson => write!(f, "poisson"),
+            Traffic::Deterministic => write!(f, "deterministic"),
+            Traffic::OnOff {
+                on,
+                off,
+                shape: None,
+            } => write!(f, "onoff={},{}", on, off),
+            Traffic::OnOff {
+                on,
+                off,
+                shape: Some(shape),
+            } => write!(f, "onoff={},{},{}", on, off, shape),
+            Traffic::Mmpp { ratio, high, low } => write!(f, "mmpp={},{},{}", ratio, high, low),
+            Traffic::Pareto(shape) => write!(f, "pareto={}", shape),
+            Traffic::Trace { ref path, .. } => write!(f, "trace={}", path),
+        }
+    }
+}
+
+// Traffic models serialize as they're specified on the command line.
+impl Serialize for Traffic {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use super::{Generator, Markov, Deterministic};
+    use super::{Generator, Markov, Deterministic, Modulated, Pareto, Trace, Traffic};
+    use trace::Record;
+    use rng::{self, Stream};
 
     // Use `cargo test -- --nocapture` to verify the generation of exponentially distributed random
     // u32 integers, at 100 packets/s and a µs scale resolution, a typical generation would be
     // [8728, 12561, 4670, 5370, 9221].
     #[test]
     fn generate_markovian_events() {
-        let mg = Markov::new(100.0);
+        let mut mg = Markov::new(100.0);
         let mut events = vec![];
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
         for _ in 0..5 {
-            events.push(mg.next_event(1e6));
+            events.push(mg.next_event(&mut rng, 1e6));
         }
-        println!("event deltas: {:?}", events)
+        println!("event deltas: {:?}", events);
+
+        // The same seed reproduces the same event deltas.
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
+        let replayed: Vec<u32> = (0..5).map(|_| mg.next_event(&mut rng, 1e6)).collect();
+        assert_eq!(events, replayed);
     }
 
     #[test]
     fn generate_deterministic_events() {
-        let dg = Deterministic::new(1000.0);
+        let mut dg = Deterministic::new(1000.0);
         let mut events = vec![];
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
         for _ in 0..5 {
-            events.push(dg.next_event(1e6));
+            events.push(dg.next_event(&mut rng, 1e6));
         }
         assert_eq!(events, vec![1000; 5]);
     }
+
+    // mean_rate returns the mean rate, in events/s, of n events drawn from the generator at a µs
+    // scale resolution.
+    fn mean_rate<G: Generator>(generator: &mut G, n: usize) -> f64 {
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
+        let total: u64 = (0..n)
+            .map(|_| u64::from(generator.next_event(&mut rng, 1e6)))
+            .sum();
+        n as f64 / (total as f64 / 1e6)
+    }
+
+    #[test]
+    fn bursty_generators_average_rate() {
+        let rate = |traffic: &str| {
+            let traffic: Traffic = traffic.parse().unwrap();
+            mean_rate(&mut traffic.build(100.0, 0), 200000)
+        };
+        for &traffic in &["poisson", "onoff=0.05,0.15", "mmpp=10,0.05,0.15"] {
+            let rate = rate(traffic);
+            assert!((rate - 100.0).abs() < 5.0, "{}: {}", traffic, rate);
+        }
+        // Heavy tails converge slowly.
+        for &traffic in &["onoff=0.05,0.15,1.8", "pareto=2.5"] {
+            let rate = rate(traffic);
+            assert!((rate - 100.0).abs() < 20.0, "{}: {}", traffic, rate);
+        }
+    }
+
+    #[test]
+    fn on_off_bursts() {
+        // ON periods average 10ms at a peak rate of 1000 events/s, followed by 90ms of silence on
+        // average: most events follow one another closely, some after a long silence.
+        let mut generator = Modulated::on_off(100.0, 0.01, 0.09, None);
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
+        let deltas: Vec<_> = (0..10000).map(|_| generator.next_event(&mut rng, 1e6)).collect();
+        let short = deltas.iter().filter(|&&d| d < 2000).count();
+        let long = deltas.iter().filter(|&&d| d > 20000).count();
+        assert!(short > 7000, "{}", short);
+        assert!(long > 500, "{}", long);
+
+        // A Poisson source at the same rate rarely sees either.
+        let mut markov = Markov::new(100.0);
+        let deltas: Vec<_> = (0..10000).map(|_| markov.next_event(&mut rng, 1e6)).collect();
+        assert!(deltas.iter().filter(|&&d| d < 2000).count() < 2500);
+    }
+
+    #[test]
+    fn pareto_interarrivals() {
+        // Interarrival times never fall short of the scale, 0.6/100s for a shape of 2.5.
+        let mut generator = Pareto::new(100.0, 2.5);
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
+        assert!((0..1000).all(|_| generator.next_event(&mut rng, 1e6) >= 6000));
+    }
+
+    #[test]
+    fn trace_replay() {
+        let records: Vec<_> = [(0.0, 0), (0.5, 1), (1.0, 0), (1.0, 0), (2.5, 0)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(time, node))| {
+                Record {
+                    time,
+                    node,
+                    length: 100 * (i as u32 + 1),
+                }
+            })
+            .collect();
+        let mut generator = Trace::new(&records, 0);
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
+        let mut events = vec![];
+        for _ in 0..5 {
+            let delta = generator.next_event(&mut rng, 10.0);
+            events.push((delta, generator.packet_length()));
+        }
+        // The packet at tick 0 is generated on first ticking the Client, that captured in the
+        // same tick as the previous one a tick later.
+        assert_eq!(
+            events,
+            vec![
+                (1, Some(100)),
+                (10, Some(300)),
+                (1, Some(400)),
+                (14, Some(500)),
+                (u32::MAX, Some(500)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_traffic() {
+        for &spec in &[
+            "poisson",
+            "deterministic",
+            "onoff=0.01,0.09",
+            "onoff=0.01,0.09,1.5",
+            "mmpp=10,0.05,0.5",
+            "pareto=1.5",
+        ]
+        {
+            let traffic: Traffic = spec.parse().unwrap();
+            assert_eq!(traffic.to_string(), spec);
+        }
+        for &spec in &[
+            "bursty",
+            "onoff=0.01",
+            "onoff=0,0.09",
+            "onoff=0.01,0.09,1",
+            "mmpp=0.5,0.05,0.5",
+            "pareto=1",
+            "pareto=",
+        ]
+        {
+            assert!(spec.parse::<Traffic>().is_err(), "{}", spec);
+        }
+    }
 }
@@ -1,6 +1,29 @@
 extern crate rand;
 extern crate bit_vec;
+extern crate stats;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
 
 pub mod generators;
 pub mod simulators;
 pub mod cbuffer;
+pub mod rng;
+pub mod topology;
+pub mod engine;
+pub mod scheduler;
+pub mod sweep;
+pub mod output;
+pub mod replication;
+pub mod backoff;
+pub mod sizes;
+pub mod trace;
+pub mod config;
+pub mod log;
+pub mod series;
+pub mod switch;
+pub mod mac;
+pub mod aloha;
+pub mod token;
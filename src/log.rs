@@ -0,0 +1,360 @@
+use std::fmt;
+use std::io::{self, Write};
+use serde::{Serialize, Serializer};
+use serde_json;
+use simulators::Packet;
+
+// Kind is what an Entry in the event log records. Every stage of a packet's lifecycle at a node
+// is logged, as is every change in the state of the channel.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    // The node's Client generated the packet.
+    Generated,
+    // The packet arrived to a full queue and was dropped.
+    TailDropped,
+    // The node started sensing the medium ahead of transmitting the packet.
+    Sensing,
+    // The p-persistent node, having sensed the medium idle, deferred its transmission by a slot.
+    Deferred,
+    // The node started transmitting the packet.
+    Transmitting,
+    // The node detected a collision, aborting the transmission and jamming the medium.
+    Collision,
+    // The node started backing off, its retry count incremented.
+    Backoff,
+    // The node pushed the last bit of the packet onto the medium.
+    Transmitted,
+    // The node ran out of attempts at transmitting the packet and dropped it.
+    Dropped,
+    // The last bit of the packet, addressed to the node, reached it.
+    Received,
+    // The channel went idle, busy with a single transmission or wasted on colliding ones (or a
+    // jam signal).
+    ChannelIdle,
+    ChannelBusy,
+    ChannelCollision,
+}
+
+impl Kind {
+    // Kind::channel returns the state of a channel with the given number of nodes transmitting,
+    // of which the given number are jamming (see ChannelStatistics.record).
+    pub fn channel(transmitters: usize, jammers: usize) -> Kind {
+        match transmitters {
+            0 => Kind::ChannelIdle,
+            1 if jammers == 0 => Kind::ChannelBusy,
+            _ => Kind::ChannelCollision,
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Kind::Generated => "generated",
+            Kind::TailDropped => "tail_dropped",
+            Kind::Sensing => "sensing",
+            Kind::Deferred => "deferred",
+            Kind::Transmitting => "transmitting",
+            Kind::Collision => "collision",
+            Kind::Backoff => "backoff",
+            Kind::Transmitted => "transmitted",
+            Kind::Dropped => "dropped",
+            Kind::Received => "received",
+            Kind::ChannelIdle => "channel_idle",
+            Kind::ChannelBusy => "channel_busy",
+            Kind::ChannelCollision => "channel_collision",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Kinds serialize as they're displayed.
+impl Serialize for Kind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// Entry is an event in the log: the tick it happened at, the node it happened to along with the
+// packet concerned (its id) and the number of times its transmission failed thus far, unknown to
+// the nodes receiving it. Channel events have neither node nor packet.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Entry {
+    pub tick: u32,
+    pub node: Option<usize>,
+    pub kind: Kind,
+    pub packet: Option<u64>,
+    pub retries: Option<u32>,
+}
+
+// Filter selects the events to log: those of the given nodes (every node if none are given)
+// happening within the window [from, until), in seconds. Channel events are logged regardless of
+// the nodes selected.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Filter {
+    pub nodes: Vec<usize>,
+    pub from: f64,
+    pub until: Option<f64>,
+}
+
+impl Filter {
+    // Filter.set sets the filter's nodes, a comma separated list, or its window, given as
+    // 'FROM..TO' with either end optional.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value '{}' for {}", value, name);
+        match name {
+            "nodes" => {
+                self.nodes = value
+                    .split(',')
+                    .map(|n| n.trim().parse().map_err(|_| invalid()))
+                    .collect::<Result<_, _>>()?
+            }
+            "window" => {
+                let mut bounds = value.splitn(2, "..");
+                let from = bounds.next().unwrap().trim();
+                let until = bounds.next().ok_or_else(invalid)?.trim();
+                self.from = if from.is_empty() {
+                    0.0
+                } else {
+                    from.parse().map_err(|_| invalid())?
+                };
+                self.until = if until.is_empty() {
+                    None
+                } else {
+                    Some(until.parse().map_err(|_| invalid())?)
+                };
+                if self.from < 0.0 || self.until.is_some_and(|until| until <= self.from) {
+                    return Err(invalid());
+                }
+            }
+            _ => return Err(format!("unknown log filter '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+// EventLog collects the events admitted by its Filter over the course of a run, the window
+// converted to ticks at the simulation's resolution.
+#[derive(Clone, Debug)]
+pub struct EventLog {
+    nodes: Vec<usize>,
+    from: u32,
+    until: u32,
+    entries: Vec<Entry>,
+}
+
+impl EventLog {
+    // EventLog::new returns an empty log for the given filter, resolution being in ticks/s.
+    pub fn new(filter: &Filter, resolution: f64) -> EventLog {
+        EventLog {
+            nodes: filter.nodes.clone(),
+            from: (filter.from * resolution) as u32,
+            until: filter.until.map_or(u32::MAX, |until| (until * resolution) as u32),
+            entries: vec![],
+        }
+    }
+
+    // EventLog.node logs an event concerning the given packet at the given node.
+    pub fn node(&mut self, tick: u32, node: usize, kind: Kind, packet: &Packet, retries: u32) {
+        if self.nodes.is_empty() || self.nodes.contains(&node) {
+            self.push(Entry {
+                tick,
+                node: Some(node),
+                kind,
+                packet: Some(packet.id),
+                retries: Some(retries),
+            });
+        }
+    }
+
+    // EventLog.received logs the given packet being received by the given node.
+    pub fn received(&mut self, tick: u32, node: usize, packet: &Packet) {
+        if self.nodes.is_empty() || self.nodes.contains(&node) {
+            self.push(Entry {
+                tick,
+                node: Some(node),
+                kind: Kind::Received,
+                packet: Some(packet.id),
+                retries: None,
+            });
+        }
+    }
+
+    // EventLog.channel logs a change in the state of the channel.
+    pub fn channel(&mut self, tick: u32, kind: Kind) {
+        self.push(Entry {
+            tick,
+            node: None,
+            kind,
+            packet: None,
+            retries: None,
+        });
+    }
+
+    fn push(&mut self, entry: Entry) {
+        if self.from <= entry.tick && entry.tick < self.until {
+            self.entries.push(entry);
+        }
+    }
+}
+
+// log::merge merges the given logs into a single one ordered by tick, then by node (channel
+// events first). The events of a node within a tick are kept in the order they happened in.
+pub fn merge(logs: Vec<EventLog>) -> Vec<Entry> {
+    let mut entries: Vec<_> = logs.into_iter().flat_map(|log| log.entries).collect();
+    entries.sort_by_key(|e| (e.tick, e.node));
+    entries
+}
+
+// log::json writes every entry as a JSON object on a line of its own (JSON Lines).
+pub fn json<W: Write>(w: &mut W, entries: &[Entry]) -> io::Result<()> {
+    for entry in entries {
+        serde_json::to_writer(&mut *w, entry)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+// log::csv writes a row per entry, the node, packet and retries left empty for channel events.
+pub fn csv<W: Write>(w: &mut W, entries: &[Entry]) -> io::Result<()> {
+    fn cell<T: ToString>(value: Option<T>) -> String {
+        value.map(|v| v.to_string()).unwrap_or_default()
+    }
+
+    writeln!(w, "tick,node,kind,packet,retries")?;
+    for e in entries {
+        writeln!(
+            w,
+            "{},{},{},{},{}",
+            e.tick,
+            cell(e.node),
+            e.kind,
+            cell(e.packet),
+            cell(e.retries)
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::{self, Params, Report};
+    use simulators::{CsmaMode, Destination};
+    use sizes::PacketSize;
+
+    fn packet(id: u32) -> Packet {
+        Packet {
+            id: u64::from(id),
+            source: 0,
+            destination: Destination::Broadcast,
+            time_generated: 0,
+            length: 1,
+        }
+    }
+
+    #[test]
+    fn filtering() {
+        let mut filter = Filter::default();
+        filter.set("nodes", "1, 3").unwrap();
+        filter.set("window", "0.5..").unwrap();
+        assert_eq!((filter.nodes.clone(), filter.from, filter.until), (vec![1, 3], 0.5, None));
+        filter.set("window", "..2").unwrap();
+        assert_eq!((filter.from, filter.until), (0.0, Some(2.0)));
+        filter.set("window", "1..2").unwrap();
+        for &(name, value) in
+            &[("nodes", "1,x"), ("window", "2"), ("window", "2..1"), ("window", "-1.."), ("x", "")]
+        {
+            assert!(Filter::default().set(name, value).is_err(), "{}={}", name, value);
+        }
+
+        let mut log = EventLog::new(&filter, 10.0);
+        for tick in 5..25 {
+            for node in 0..4 {
+                log.node(tick, node, Kind::Generated, &packet(tick), 0);
+            }
+            log.channel(tick, Kind::ChannelIdle);
+        }
+        let entries = merge(vec![log]);
+        assert_eq!(entries.len(), 10 * 3);
+        assert!(entries.iter().all(|e| 10 <= e.tick && e.tick < 20));
+        assert!(entries.iter().all(|e| e.node.is_none_or(|n| n == 1 || n == 3)));
+        assert_eq!((entries[0].node, entries[1].node, entries[2].node), (None, Some(1), Some(3)));
+    }
+
+    #[test]
+    fn formats() {
+        let mut log = EventLog::new(&Filter::default(), 1.0);
+        log.node(2, 0, Kind::Backoff, &packet(7), 1);
+        log.channel(1, Kind::channel(2, 0));
+        let entries = merge(vec![log]);
+
+        let mut buf = vec![];
+        csv(&mut buf, &entries).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "tick,node,kind,packet,retries\n1,,channel_collision,,\n2,0,backoff,7,1\n"
+        );
+
+        let mut buf = vec![];
+        json(&mut buf, &entries).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"tick\":1,\"node\":null,\"kind\":\"channel_collision\",\"packet\":null,\
+             \"retries\":null}\n{\"tick\":2,\"node\":0,\"kind\":\"backoff\",\"packet\":7,\
+             \"retries\":1}\n"
+        );
+    }
+
+    // A run logs every packet generated, transmitted and dropped and every collision, in order,
+    // and the filter only keeps the events of its nodes and window.
+    #[test]
+    fn logged_run() {
+        let logged = |filter: &Filter| {
+            engine::run(&Params {
+                rate: 200,
+                psize: PacketSize::Constant(1000),
+                duration: 1,
+                qsize: Some(4),
+                csma: CsmaMode::PPersistent(0.5),
+                attempts: 3,
+                truncation: 2,
+                resolution: 1e5,
+                log: Some(filter.clone()),
+                seed: 8,
+                ..Params::default()
+            })
+        };
+        let count = |report: &Report, kind| report.log.iter().filter(|e| e.kind == kind).count();
+
+        let report = logged(&Filter::default());
+        assert_eq!(count(&report, Kind::Generated) as u32, report.packets_generated());
+        assert_eq!(count(&report, Kind::Transmitted) as u32, report.packets_processed());
+        assert_eq!(count(&report, Kind::Dropped) as u32, report.packets_dropped());
+        assert_eq!(count(&report, Kind::TailDropped) as u32, report.packets_tail_dropped());
+        assert_eq!(count(&report, Kind::Collision) as u32, report.collisions());
+        assert!(count(&report, Kind::Deferred) > 0);
+        assert!(count(&report, Kind::ChannelCollision) > 0);
+        assert!(report.log.windows(2).all(|w| w[0].tick <= w[1].tick));
+
+        // A dropped packet was retried as many times as there are attempts.
+        let dropped = report.log.iter().find(|e| e.kind == Kind::Dropped).unwrap();
+        assert_eq!(dropped.retries, Some(3));
+
+        let mut filter = Filter::default();
+        filter.set("nodes", "2").unwrap();
+        filter.set("window", "0.25..0.5").unwrap();
+        let filtered = logged(&filter);
+        assert!(!filtered.log.is_empty());
+        assert_eq!(
+            filtered.log,
+            report
+                .log
+                .iter()
+                .filter(|e| e.node.is_none_or(|n| n == 2))
+                .filter(|e| 25_000 <= e.tick && e.tick < 50_000)
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+    }
+}
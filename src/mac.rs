@@ -0,0 +1,230 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+use bit_vec::BitVec;
+use serde::{Serialize, Serializer};
+use aloha::{self, Aloha};
+use engine::Params;
+use log::{self, EventLog};
+use simulators::{Csma, Medium, Packet, Queue, ServerStatistics};
+use token::{Ring, Token, TOKEN_BITS};
+
+// Station is what a MacProtocol gets to see of, and do to, the node it runs on over a tick: the
+// packets queued at the node, the state of the medium where the node is attached, the node's
+// statistics and its event log. The MAC records the collisions it detects and the packets it
+// drops in the statistics, the Server those it transmits.
+pub struct Station<'a> {
+    pub id: usize,
+    pub now: u32,
+    pub queue: &'a mut Queue,
+    pub statistics: &'a mut ServerStatistics,
+    pub log: &'a mut Option<EventLog>,
+    pub medium: &'a Medium,
+    pub local_state: &'a mut BitVec,
+}
+
+impl<'a> Station<'a> {
+    // Station.busy returns whether the node senses another node's signal on the medium.
+    pub fn busy(&self) -> bool {
+        self.medium.is_busy(self.id)
+    }
+
+    // Station.transmit has the node write to the medium this tick.
+    pub fn transmit(&mut self) {
+        self.local_state.set(self.id, true);
+    }
+
+    // Station.log logs an event concerning the given packet, if the node has an EventLog.
+    pub fn log(&mut self, kind: log::Kind, packet: &Packet, retries: u32) {
+        if let Some(ref mut log) = *self.log {
+            log.node(self.now, self.id, kind, packet, retries);
+        }
+    }
+}
+
+// MacProtocol is a medium access control scheme, the state machine deciding when a node
+// transmits the packets queued at it. It's driven tick by tick, observing the medium and the
+// node's queue through the Station.
+pub trait MacProtocol {
+    // MacProtocol.tick moves the protocol a tick forward. It returns the packet whose last bit the
+    // node wrote to the medium this tick, if any, along with the number of times its transmission
+    // failed before.
+    fn tick(&mut self, station: &mut Station) -> Option<(Packet, u32)>;
+
+    // MacProtocol.is_jamming returns whether the node jammed the medium on its last tick, the
+    // node still counting as transmitting (see ChannelStatistics.record).
+    fn is_jamming(&self) -> bool {
+        false
+    }
+}
+
+impl<M: MacProtocol + ?Sized> MacProtocol for Box<M> {
+    fn tick(&mut self, station: &mut Station) -> Option<(Packet, u32)> {
+        (**self).tick(station)
+    }
+
+    fn is_jamming(&self) -> bool {
+        (**self).is_jamming()
+    }
+}
+
+// Mac selects one of the MacProtocol implementations, as specified on the command line: 'csma'
+// for CSMA/CD, as per the CSMA mode, backoff policy and attempts of Params, 'aloha' and
+// 'slotted-aloha' for pure and slotted ALOHA, retransmitting as per the backoff policy and
+// attempts too. ALOHA slots are as long as it takes to transmit a packet of the LAN-wide mean
+// size, plus the end-to-end propagation delay of the bus as a guard time. 'token' passes a token
+// around the nodes as per the token parameters of Params.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mac {
+    Csma,
+    Aloha,
+    SlottedAloha,
+    Token,
+}
+
+impl Mac {
+    // Mac.build returns the MacProtocol of every node, indexed by id.
+    pub fn build(&self, params: &Params) -> Vec<Box<dyn MacProtocol>> {
+        let timing = params.timing();
+        let slot = timing.ticks(params.psize.mean() / timing.lspeed) +
+            timing.ticks(params.topology().length() / params.speed);
+        let ring = Rc::new(RefCell::new(Ring::new(
+            params.ncount,
+            timing.ticks(params.tlatency.unwrap_or(TOKEN_BITS / timing.lspeed)),
+            params.tloss,
+            timing.ticks(params.tregen),
+            params.seed,
+        )));
+        (0..params.ncount)
+            .map(|id| -> Box<dyn MacProtocol> {
+                match *self {
+                    Mac::Csma => Box::new(
+                        Csma::new(id, timing, params.node(id).csma, params.seed)
+                            .with_backoff(params.backoff_policy().build(params.truncation))
+                            .with_attempts(params.attempts),
+                    ),
+                    Mac::Aloha | Mac::SlottedAloha => Box::new(
+                        Aloha::new(id, timing, slot, *self == Mac::SlottedAloha, params.seed)
+                            .with_backoff(params.backoff_policy().build(params.truncation))
+                            .with_attempts(params.attempts),
+                    ),
+                    Mac::Token => Box::new(
+                        Token::new(id, ring.clone(), timing, timing.ticks(params.tht)),
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    // Mac.throughput returns the throughput, as a fraction of the LAN speed, theory predicts for
+    // the protocol under the given offered load (see Report.offered_load), if known.
+    pub fn throughput(&self, load: f64) -> Option<f64> {
+        match *self {
+            Mac::Csma | Mac::Token => None,
+            Mac::Aloha => Some(aloha::throughput(false, load)),
+            Mac::SlottedAloha => Some(aloha::throughput(true, load)),
+        }
+    }
+}
+
+impl FromStr for Mac {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csma" => Ok(Mac::Csma),
+            "aloha" => Ok(Mac::Aloha),
+            "slotted-aloha" => Ok(Mac::SlottedAloha),
+            "token" => Ok(Mac::Token),
+            _ => Err(format!(
+                "unknown MAC protocol '{}', expected 'csma', 'aloha', 'slotted-aloha' or 'token'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Mac {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mac::Csma => write!(f, "csma"),
+            Mac::Aloha => write!(f, "aloha"),
+            Mac::SlottedAloha => write!(f, "slotted-aloha"),
+            Mac::Token => write!(f, "token"),
+        }
+    }
+}
+
+// Macs serialize as they're specified on the command line.
+impl Serialize for Mac {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generators::Deterministic;
+    use simulators::{CsmaMode, Server, Timing};
+    use sizes::Constant;
+
+    // Greedy transmits every packet as soon as it's queued, a bit per tick, whatever the state of
+    // the medium.
+    struct Greedy {
+        current: Option<(Packet, u32)>,
+    }
+
+    impl MacProtocol for Greedy {
+        fn tick(&mut self, station: &mut Station) -> Option<(Packet, u32)> {
+            if self.current.is_none() {
+                self.current = station.queue.pop().map(|packet| (packet, 0));
+            }
+            let (packet, bits) = self.current?;
+            station.transmit();
+            if bits + 1 == packet.length {
+                self.current = None;
+                return Some((packet, 0));
+            }
+            self.current = Some((packet, bits + 1));
+            None
+        }
+    }
+
+    #[test]
+    fn pluggable_mac() {
+        // The medium is busy throughout, which Greedy pays no heed to.
+        let mut medium = Medium::new(2, 1);
+        let mut server = Server::new(
+            0, // id
+            Deterministic::new(0.1), // generator
+            Box::new(Constant(5)), // psize
+            Timing::new(1.0, 1.0), // timing
+            CsmaMode::NonPersistent, // csma
+            None, // qsize
+            0, // seed
+        ).with_mac(Greedy { current: None });
+        let mut transmitting = 0;
+        for i in 0..105 {
+            let mut state = BitVec::from_elem(2, false);
+            state.set(1, true);
+            server.tick(&mut state, &medium, i);
+            if state[0] {
+                transmitting += 1;
+            }
+            medium.write(state);
+            medium.tick();
+        }
+        let statistics = server.statistics();
+        assert_eq!(statistics.packets_generated, 10);
+        assert_eq!(statistics.packets_processed, 10);
+        assert_eq!(statistics.collisions, 0);
+        assert_eq!(transmitting, 50);
+
+        assert_eq!("csma".parse(), Ok(Mac::Csma));
+        assert_eq!("slotted-aloha".parse(), Ok(Mac::SlottedAloha));
+        assert_eq!("token".parse(), Ok(Mac::Token));
+        assert!("polling".parse::<Mac>().is_err());
+    }
+}
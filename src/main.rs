@@ -1,54 +1,31 @@
 extern crate nlib;
 extern crate getopts;
-extern crate stats;
-extern crate bit_vec;
+extern crate rand;
 
-use bit_vec::BitVec;
 use getopts::Options;
-use nlib::generators::*;
-use nlib::simulators::*;
-use stats::OnlineStats;
+use nlib::config;
+use nlib::engine::*;
+use nlib::log::{self, Filter};
+use nlib::output::{self, Format};
+use nlib::replication;
+use nlib::sweep::Sweep;
+use rand::{thread_rng, Rng};
 use std::env;
-use std::fmt;
-
-const DEFAULT_RATE: u32 = 10;
-const DEFAULT_PSIZE: u32 = 1;
-const DEFAULT_LSPEED: u32 = 1_000_000;
-const DEFAULT_DURATION: u32 = 5;
-const DEFAULT_SERVER_COUNT: usize = 10;
-const DEFAULT_PERSISTENCE: bool = false;
-
-struct Params {
-    rate: u32,
-    psize: u32,
-    lspeed: u32,
-    duration: u32,
-    ncount: usize,
-    persistence: bool,
-    resolution: f64,
-}
-
-impl fmt::Display for Params {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Simulation configuration:").unwrap();
-        writeln!(f, "\t Rate:                  {} packets/s", self.rate).unwrap();
-        writeln!(f, "\t Packet size:           {} bits", self.psize).unwrap();
-        writeln!(f, "\t LAN speed:             {} bits/s", self.lspeed).unwrap();
-        writeln!(f, "\t Simulation duration:   {}s", self.duration).unwrap();
-        writeln!(f, "\t Server count:          {} Clients", self.ncount).unwrap();
-        writeln!(f, "\t CSMA/CD Persistence:   {}", self.persistence).unwrap();
-        writeln!(f, "\t Resolution:            1µs").unwrap(); // TODO(irfansharif).
-        write!(
-            f,
-            "\t Ticks per packet:      {}",
-            f64::from(self.psize) / f64::from(self.lspeed) * self.resolution
-        )
-    }
-}
+use std::fs::File;
+use std::io::{self, BufWriter};
 
 fn construct_options() -> Options {
     let mut opts = Options::new();
     opts.optflag("h", "help", "Display this message");
+    opts.optopt(
+        "",
+        "config",
+        "TOML file configuring the LAN, setting any of the options below by name and \
+         describing groups of nodes with their own rate, traffic, psize, qsize, csma and dest in \
+         [[node]] tables (see src/config.rs), options given on the command line taking \
+         precedence",
+        "FILE",
+    );
     opts.optopt(
         "",
         "rate",
@@ -61,8 +38,33 @@ fn construct_options() -> Options {
     opts.optopt(
         "",
         "psize",
-        &format!("Packet size; bits (def: {})", DEFAULT_PSIZE),
-        "NUM",
+        &format!(
+            "Packet size, constant or drawn from a uniform, exponential, bimodal or empirical \
+             distribution, the latter read from a file of 'LENGTH WEIGHT' lines; bits (def: {})",
+            DEFAULT_PSIZE
+        ),
+        "NUM|uniform=MIN,MAX|exponential=MEAN|bimodal=SMALL,LARGE,FRACTION|empirical=FILE",
+    );
+    opts.optflag(
+        "",
+        "ethernet",
+        "Enforce the Ethernet frame size limits, padding packets to 64 bytes and capping them at \
+         1518 bytes",
+    );
+    opts.optopt(
+        "",
+        "traffic",
+        &format!(
+            "Traffic model, averaging the configured rate: Poisson arrivals, evenly spaced ones, \
+             an ON/OFF source with exponentially (or, given a shape, Pareto) distributed ON and \
+             OFF periods of the given mean lengths in seconds, a two-state Markov-modulated \
+             Poisson process with the given ratio of its high to low rate and mean periods, \
+             Pareto distributed interarrival times, or the replay of a trace, either a CSV file \
+             of 'time,node,length' rows (in seconds and bits) or an Ethernet pcap capture \
+             (def: {})",
+            DEFAULT_TRAFFIC
+        ),
+        "poisson|deterministic|onoff=ON,OFF[,SHAPE]|mmpp=RATIO,HIGH,LOW|pareto=SHAPE|trace=FILE",
     );
     opts.optopt(
         "",
@@ -91,54 +93,342 @@ fn construct_options() -> Options {
         ),
         "NUM",
     );
-    opts.optflag(
+    opts.optopt(
+        "",
+        "spacing",
+        &format!(
+            "Distance between adjacent nodes on the bus, the bus and so its end-to-end \
+             propagation delay growing with --ncount (25.6us for the default 10 nodes); meters \
+             (def: {})",
+            DEFAULT_SPACING
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "speed",
+        &format!(
+            "Signal propagation speed along the bus; meters/s (def: {})",
+            DEFAULT_SPEED
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "dest",
+        &format!(
+            "Destination of every packet, a node drawn uniformly at random from the others, \
+             every other node or the given node, whose own packets go to a node drawn at random \
+             (def: {})",
+            DEFAULT_DEST
+        ),
+        "uniform|broadcast|NUM",
+    );
+    opts.optopt(
+        "",
+        "lan",
+        &format!(
+            "How nodes are interconnected, sharing a single collision domain or each linked to \
+             a store-and-forward switch over a dedicated full-duplex link, --spacing meters long, \
+             never contended for, --mac and the token, CSMA/CD and backoff options not applying \
+             (def: {})",
+            DEFAULT_LAN
+        ),
+        "bus|switch",
+    );
+    opts.optopt(
+        "",
+        "pbuffer",
+        "Per-port buffer size of the switch, packets forwarded to a full buffer are dropped; \
+         packets (def: unbounded)",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "latency",
+        &format!(
+            "Forwarding latency of the switch, from receiving a packet whole to queueing it at \
+             its output port; seconds (def: {})",
+            DEFAULT_LATENCY
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "mac",
+        &format!(
+            "Medium access control protocol of the nodes on the bus, CSMA/CD as configured by \
+             the options below, pure or slotted ALOHA, retransmitting after a random number of \
+             packet times as per --backoff and --attempts, or token passing around the nodes in \
+             the order of their ids (def: {})",
+            DEFAULT_MAC
+        ),
+        "csma|aloha|slotted-aloha|token",
+    );
+    opts.optopt(
+        "",
+        "tht",
+        &format!(
+            "Token holding time, past which a node passes the token on rather than transmit \
+             another packet; seconds (def: {})",
+            DEFAULT_THT
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "tlatency",
+        "Time it takes to pass the token on to the next node; seconds (def: that of \
+         transmitting a 24 bit token)",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "tloss",
+        &format!(
+            "Probability of losing the token every time it's passed on (def: {})",
+            DEFAULT_TLOSS
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "tregen",
+        &format!(
+            "Time it takes node 0, monitoring the ring, to regenerate a lost token; seconds \
+             (def: {})",
+            DEFAULT_TREGEN
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "csma",
+        &format!(
+            "CSMA/CD mode, non-persistent nodes back off on sensing a busy medium while \
+             1-persistent ones wait for it to be idle and transmit, p-persistent ones only doing \
+             so with probability p (def: {})",
+            DEFAULT_CSMA
+        ),
+        "nonpersistent|1|p=NUM",
+    );
+    opts.optopt(
+        "",
+        "backoff",
+        &format!(
+            "Backoff policy, the contention window growing exponentially or along the \
+             Fibonacci sequence (both truncated, see --truncation), linearly with every retry, \
+             staying constant or being looked up by retry in a table of windows, in slots \
+             (def: {} for CSMA/CD, {} for ALOHA)",
+            DEFAULT_BACKOFF,
+            DEFAULT_ALOHA_BACKOFF
+        ),
+        "beb|linear|constant=NUM|fibonacci|table=NUM,NUM,..",
+    );
+    opts.optopt(
+        "",
+        "attempts",
+        &format!(
+            "Number of failed attempts at transmitting a packet after which it is dropped \
+             (def: {})",
+            DEFAULT_ATTEMPTS
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "truncation",
+        &format!(
+            "Number of retries past which the exponential and Fibonacci backoff windows stop \
+             growing (def: {})",
+            DEFAULT_TRUNCATION
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "qsize",
+        "Per-node buffer size, packets arriving to a full buffer are dropped; packets \
+         (def: unbounded)",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "resolution",
+        &format!(
+            "Simulation resolution; ticks/s, coarser resolutions trade fidelity for speed \
+             (def: {})",
+            DEFAULT_RESOLUTION
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "seed",
+        "Seed for the simulation's random number generators, runs with the same seed and \
+         parameters are identical (def: random)",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "sweep",
+        "Simulate every value of a parameter, given as an inclusive range or a list, printing \
+         a row of results per value",
+        "NAME=FROM..TO:STEP|NAME=V1,V2,..",
+    );
+    opts.optopt(
+        "",
+        "warmup",
+        &format!(
+            "Warm-up period whose statistics are discarded; seconds (def: {})",
+            DEFAULT_WARMUP
+        ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "replications",
+        &format!(
+            "Number of independent replications, seeded successively from --seed, to estimate \
+             confidence intervals from; every replication is written out with --format json|csv \
+             (def: {})",
+            DEFAULT_REPLICATIONS
+        ),
+        "NUM",
+    );
+    opts.optopt(
         "",
-        "persistence",
+        "confidence",
         &format!(
-            "Simulate 1-persistent CSMA/CD protocol (def: {:?})",
-            DEFAULT_PERSISTENCE
+            "Confidence level of the intervals estimated across replications (def: {})",
+            DEFAULT_CONFIDENCE
         ),
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "sample",
+        "Sample the run every interval, from its very start, recording every node's queue \
+         length, the throughput thus far, the collisions over the interval and the fraction of it \
+         the channel was busy, written out as a time series; seconds (def: none)",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "threads",
+        "Number of threads to spread replications and sweep points across (def: 1)",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "format",
+        "Output format, 'json' writes a JSON object per line and 'csv' a row per run and per \
+         node, both including the simulation configuration (def: text)",
+        "text|json|csv",
+    );
+    opts.optopt(
+        "",
+        "engine",
+        &format!(
+            "Simulation engine, 'tick' walks every tick while 'event' jumps between \
+             discrete events (def: {})",
+            DEFAULT_ENGINE
+        ),
+        "tick|event",
+    );
+    opts.optopt(
+        "",
+        "log",
+        "Log every stage of every packet's lifecycle (generated, sensing, deferred, \
+         transmitting, collision, backoff, transmitted, dropped) and every change in the state of \
+         the channel to FILE, tick by tick; single runs only",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "log-format",
+        "Event log format, 'json' writes a JSON object per event and 'csv' a row (def: json)",
+        "json|csv",
+    );
+    opts.optopt(
+        "",
+        "log-nodes",
+        "Only log the events of the given nodes, channel events aside (def: all)",
+        "NUM,NUM,..",
+    );
+    opts.optopt(
+        "",
+        "log-window",
+        "Only log the events within the given window, either end of which may be left out; \
+         seconds (def: the whole run)",
+        "FROM..TO",
     );
     opts
 }
 
-fn parse_params(matches: &getopts::Matches) -> Params {
-    let rate = match matches.opt_str("rate") {
-        Some(x) => x.parse::<u32>().unwrap(),
-        None => DEFAULT_RATE,
-    };
-    let psize = match matches.opt_str("psize") {
-        Some(x) => x.parse::<u32>().unwrap(),
-        None => DEFAULT_PSIZE,
-    };
-    let lspeed = match matches.opt_str("lspeed") {
-        Some(x) => x.parse::<u32>().unwrap(),
-        None => DEFAULT_LSPEED,
-    };
-    let duration = match matches.opt_str("duration") {
-        Some(x) => x.parse::<u32>().unwrap(),
-        None => DEFAULT_DURATION,
-    };
-    let ncount = match matches.opt_str("ncount") {
-        Some(x) => x.parse::<usize>().unwrap(),
-        None => DEFAULT_SERVER_COUNT,
-    };
-    let persistence = if matches.opt_present("persistence") {
-        true
-    } else {
-        DEFAULT_PERSISTENCE
-    };
-    let resolution = 1e6; // TODO(irfansharif).
+// PARAMS are the command line options that map onto Params, named as they are in Params.set.
+const PARAMS: &[&str] = &[
+    "rate",
+    "psize",
+    "traffic",
+    "lspeed",
+    "duration",
+    "ncount",
+    "spacing",
+    "speed",
+    "dest",
+    "lan",
+    "pbuffer",
+    "latency",
+    "mac",
+    "tht",
+    "tlatency",
+    "tloss",
+    "tregen",
+    "csma",
+    "backoff",
+    "attempts",
+    "truncation",
+    "qsize",
+    "resolution",
+    "seed",
+    "engine",
+    "warmup",
+    "replications",
+    "confidence",
+    "sample",
+];
 
-    Params {
-        rate,
-        psize,
-        lspeed,
-        duration,
-        ncount,
-        persistence,
-        resolution,
+// parse_params builds the Params of the simulation, those given on the command line taking
+// precedence over those in the configuration file, if any. It fails on any bad option, or on a
+// combination of them the simulation doesn't support (see Params.validate).
+fn parse_params(matches: &getopts::Matches) -> Result<Params, String> {
+    let mut params = Params {
+        seed: thread_rng().gen(),
+        ..Params::default()
+    };
+    if let Some(path) = matches.opt_str("config") {
+        config::load(&path, &mut params)?;
+    }
+    for name in PARAMS {
+        if let Some(x) = matches.opt_str(name) {
+            params.set(name, &x)?;
+        }
+    }
+    if matches.opt_present("ethernet") {
+        params.ethernet = true;
+    }
+    if matches.opt_present("log") {
+        let mut filter = Filter::default();
+        for name in &["nodes", "window"] {
+            if let Some(x) = matches.opt_str(&format!("log-{}", name)) {
+                filter.set(name, &x)?;
+            }
+        }
+        params.log = Some(filter);
     }
+    params.validate()?;
+    Ok(params)
 }
 
 fn print_usage(program: &str, opts: &Options) {
@@ -146,6 +436,13 @@ fn print_usage(program: &str, opts: &Options) {
     print!("{}", opts.usage(&brief));
 }
 
+// fail reports the illegal usage of the program, the given error, along with its usage and exits.
+fn fail(program: &str, opts: &Options, error: &str) -> ! {
+    println!("{}: illegal usage -- {}", program, error);
+    print_usage(program, opts);
+    std::process::exit(1)
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     let program = args[0].clone();
@@ -153,11 +450,7 @@ fn main() {
     let opts = construct_options();
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
-        Err(f) => {
-            println!("{}: illegal usage -- {}", program, f.to_string());
-            print_usage(&program, &opts);
-            std::process::exit(1)
-        }
+        Err(f) => fail(&program, &opts, &f.to_string()),
     };
 
     if matches.opt_present("h") {
@@ -165,69 +458,321 @@ fn main() {
         return;
     }
 
-    let params = parse_params(&matches);
-    println!("{}", params);
-
-    let ticks = params.duration * params.resolution as u32;
-    let mut servers: Vec<_> = (0..params.ncount)
-        .map(|id| {
-            Server::new(
-                id,
-                Markov::new(f64::from(params.rate)),
-                params.psize,
-                params.resolution,
-                f64::from(params.lspeed),
-                params.persistence,
-            )
+    let params = parse_params(&matches).unwrap_or_else(|e| fail(&program, &opts, &e));
+    let format = match matches.opt_str("format") {
+        Some(x) => x.parse::<Format>().unwrap_or_else(|e| fail(&program, &opts, &e)),
+        None => Format::Text,
+    };
+    let sweep = matches.opt_str("sweep").map(|x| {
+        x.parse::<Sweep>().unwrap_or_else(|e| fail(&program, &opts, &e))
+    });
+
+    let log_format = match matches.opt_str("log-format") {
+        Some(x) => x.parse::<Format>().unwrap_or_else(|e| fail(&program, &opts, &e)),
+        None => Format::Json,
+    };
+    if log_format == Format::Text {
+        fail(&program, &opts, "the event log is written as either json or csv");
+    }
+    if params.log.is_some() && (sweep.is_some() || params.replications > 1) {
+        fail(&program, &opts, "only single runs can be logged, not sweeps or replications");
+    }
+
+    let threads = match matches.opt_str("threads") {
+        Some(x) => x.parse::<usize>().unwrap(),
+        None => 1,
+    };
+
+    // Every point of the sweep is replicated, all replications are simulated at once and then
+    // grouped back by point.
+    let points = match sweep {
+        Some(ref sweep) => sweep.params(&params).unwrap_or_else(|e| fail(&program, &opts, &e)),
+        None => vec![params.clone()],
+    };
+    let runs = replication::run(
+        points.iter().flat_map(replication::replicas).collect(),
+        threads,
+    );
+    let mut start = 0;
+    let points: Vec<_> = points
+        .iter()
+        .map(|p| {
+            start += p.replications as usize;
+            &runs[start - p.replications as usize..start]
         })
         .collect();
 
-    let mut pstats = OnlineStats::new();
-
-    // Hardcode a 25.6 (rounding up to 26) microsecond delay
-    let mut medium = Medium::new(params.ncount, 26);
-
-    for i in 0..ticks {
-        // TODO(irfansharif): Look at and try to use smart pointers, share link ownership with
-        // Clients and the Server such that the main loop body simply ticks all participants instead of
-        // additionally shuffling data around.
-        let mut local_state = BitVec::from_elem(params.ncount, false);
-        // TODO: Be able to handle multiple packet output
-        // With a packet length of 1000, its impossible for more than 1 packet to be outputted at a given tick
-        for server in servers.iter_mut() {
-            if let Some(p) = server.tick(&mut local_state, &medium, i) {
-                pstats.add(f64::from(i - p.time_generated) / params.resolution);
+    if let Some(path) = matches.opt_str("log") {
+        let mut w = BufWriter::new(File::create(&path).unwrap());
+        let entries = &runs[0].1.log;
+        match log_format {
+            Format::Csv => log::csv(&mut w, entries),
+            _ => log::json(&mut w, entries),
+        }.unwrap();
+    }
+
+    let stdout = io::stdout();
+    match format {
+        Format::Text => {
+            println!("{}", params);
+            match sweep {
+                Some(ref sweep) if params.replications > 1 => {
+                    for (value, runs) in sweep.values.iter().zip(points) {
+                        println!("{} = {}:", sweep.name, value);
+                        print_estimates(runs, params.confidence);
+                    }
+                }
+                Some(ref sweep) => print_sweep(sweep, &runs),
+                None if params.replications > 1 => print_estimates(points[0], params.confidence),
+                None => print_report(&points[0][0].0, &points[0][0].1),
             }
         }
-        medium.write(local_state);
-        medium.tick();
+        Format::Json => output::json(&mut stdout.lock(), &runs).unwrap(),
+        Format::Csv => output::csv(&mut stdout.lock(), &runs).unwrap(),
     }
+}
 
+fn print_report(params: &Params, report: &Report) {
     println!("Simulation results:");
     println!(
         "\t Average sojourn time:              {:.4} +/- {:.4} seconds",
-        pstats.mean(),
-        pstats.stddev()
+        report.sojourn.mean(),
+        report.sojourn.stddev()
+    );
+    println!(
+        "\t Average end-to-end delay:          {:.4} +/- {:.4} seconds",
+        report.delay().mean(),
+        report.delay().stddev()
     );
-    let packets_generated: u32 = servers
-        .iter()
-        .map(|server| server.packets_generated())
-        .sum();
     println!(
         "\t Packets generated:                 {} packets",
-        packets_generated
+        report.packets_generated()
     );
-    let packets_processed: u32 = servers
-        .iter()
-        .map(|server| server.packets_processed())
-        .sum();
     println!(
         "\t Packets processed:                 {} packets",
-        packets_processed
+        report.packets_processed()
     );
-    let packets_dropped: u32 = servers.iter().map(|server| server.packets_dropped()).sum();
     println!(
         "\t Packets dropped:                   {} packets",
-        packets_dropped
+        report.packets_dropped()
+    );
+    println!(
+        "\t Packets tail-dropped:              {} packets",
+        report.packets_tail_dropped()
+    );
+    println!(
+        "\t Packets received:                  {} packets",
+        report.packets_received()
+    );
+    println!(
+        "\t Throughput:                        {:.0} bits/s ({:.2}% of LAN speed)",
+        report.throughput(),
+        report.efficiency() * 100.0
+    );
+    println!(
+        "\t Offered load:                      {:.4} attempts/packet time",
+        report.offered_load()
+    );
+    if let Some(throughput) = params
+        .mac
+        .throughput(report.offered_load())
+        .filter(|_| params.lan == Lan::Bus)
+    {
+        println!(
+            "\t Throughput in theory:              {:.2}% of LAN speed ({})",
+            throughput * 100.0,
+            params.mac
+        );
+    }
+    println!(
+        "\t Channel utilization:               {:.2}%",
+        report.channel.utilization() * 100.0
+    );
+    println!(
+        "\t Collision time:                    {:.2}%",
+        report.channel.collision_fraction() * 100.0
+    );
+    println!(
+        "\t Idle time:                         {:.2}%",
+        report.channel.idle_fraction() * 100.0
+    );
+    println!(
+        "\t Jam time:                          {:.2}%",
+        report.channel.jam_fraction() * 100.0
+    );
+    println!(
+        "\t Wasted time:                       {:.2}%",
+        report.channel.wasted_fraction() * 100.0
     );
+    println!(
+        "\t Collisions detected:               {}",
+        report.collisions()
+    );
+    println!(
+        "\t Fairness index:                    {:.4}",
+        report.fairness()
+    );
+    if !report.ports.is_empty() {
+        println!(
+            "\t Packets dropped at switch ports:   {} packets",
+            report.packets_port_dropped()
+        );
+    }
+    print_nodes(report);
+    if !report.ports.is_empty() {
+        print_ports(report);
+    }
+    if !report.series.is_empty() {
+        print_series(report);
+    }
+}
+
+// print_estimates prints the mean of every metric across the given replications along with the
+// half-width of its confidence interval.
+fn print_estimates(runs: &[(Params, Report)], confidence: f64) {
+    println!(
+        "Replication results ({} replications, {}% confidence):",
+        runs.len(),
+        confidence * 100.0
+    );
+    for (name, estimate) in replication::estimates(runs, confidence) {
+        println!(
+            "\t {:<20} {:>16.6} +/- {:.6}",
+            name,
+            estimate.mean,
+            estimate.half_width
+        );
+    }
+}
+
+// print_nodes prints a table with a row per node. Throughput is in bits/s, its share in
+// percentages, sojourn times and the end-to-end delay of the packets received in seconds and
+// retries lists the number of packets processed after 0, 1, 2.. retries.
+fn print_nodes(report: &Report) {
+    println!("Per-node results:");
+    println!(
+        "\t {:>4} {:>10} {:>10} {:>10} {:>12} {:>10} {:>12} {:>8} {:>10} {:>10} {:>10} {:>10} \
+         {:>10}  retries",
+        "node",
+        "generated",
+        "processed",
+        "dropped",
+        "tail-dropped",
+        "collisions",
+        "throughput",
+        "share",
+        "sojourn",
+        "+/-",
+        "received",
+        "delay",
+        "+/-"
+    );
+    for (id, statistics) in report.statistics.iter().enumerate() {
+        let retries: Vec<_> = statistics.retries.iter().map(|n| n.to_string()).collect();
+        println!(
+            "\t {:>4} {:>10} {:>10} {:>10} {:>12} {:>10} {:>12.0} {:>8.2} {:>10.4} {:>10.4} {:>10} \
+             {:>10.4} {:>10.4}  {}",
+            id,
+            statistics.packets_generated,
+            statistics.packets_processed,
+            statistics.packets_dropped,
+            statistics.packets_tail_dropped,
+            statistics.collisions,
+            report.node_throughput(id),
+            report.share(id) * 100.0,
+            statistics.sojourn.mean(),
+            statistics.sojourn.stddev(),
+            statistics.packets_received,
+            statistics.delay.mean(),
+            statistics.delay.stddev(),
+            retries.join(" ")
+        );
+    }
+}
+
+// print_ports prints a table with a row per output port of the switch, named after the node it's
+// linked to. Throughput is in bits/s.
+fn print_ports(report: &Report) {
+    println!("Switch ports:");
+    println!("\t {:>4} {:>10} {:>12} {:>10}", "port", "forwarded", "throughput", "dropped");
+    for (id, port) in report.ports.iter().enumerate() {
+        println!(
+            "\t {:>4} {:>10} {:>12.0} {:>10}",
+            id,
+            port.packets_forwarded,
+            port.bits_forwarded as f64 / report.duration,
+            port.packets_port_dropped
+        );
+    }
+}
+
+// print_series prints a table with a row per sample of the run. Times are in seconds, throughput
+// (thus far) in bits/s, the busy fraction of the channel over the interval in percentages and
+// queues lists the number of packets queued at every node.
+fn print_series(report: &Report) {
+    println!("Time series:");
+    println!(
+        "\t {:>10} {:>12} {:>10} {:>8}  queues",
+        "time",
+        "throughput",
+        "collisions",
+        "busy"
+    );
+    for sample in &report.series {
+        let queues: Vec<_> = sample.queues.iter().map(|n| n.to_string()).collect();
+        println!(
+            "\t {:>10.4} {:>12.0} {:>10} {:>8.2}  {}",
+            sample.time,
+            sample.throughput,
+            sample.collisions,
+            sample.busy * 100.0,
+            queues.join(" ")
+        );
+    }
+}
+
+// print_sweep prints a table with a row per point of the sweep. Throughput is in bits/s, sojourn
+// times in seconds and fractions of time in percentages.
+fn print_sweep(sweep: &Sweep, runs: &[(Params, Report)]) {
+    println!("Sweep results:");
+    println!(
+        "\t {:>10} {:>10} {:>10} {:>10} {:>12} {:>10} {:>12} {:>10} {:>8} {:>11} {:>10} {:>8} \
+         {:>8} {:>10} {:>10}",
+        sweep.name,
+        "generated",
+        "processed",
+        "dropped",
+        "tail-dropped",
+        "collisions",
+        "throughput",
+        "efficiency",
+        "load",
+        "utilization",
+        "collision",
+        "idle",
+        "fairness",
+        "sojourn",
+        "+/-"
+    );
+    for (value, (_, report)) in sweep.values.iter().zip(runs) {
+        println!(
+            "\t {:>10} {:>10} {:>10} {:>10} {:>12} {:>10} {:>12.0} {:>10.2} {:>8.4} {:>11.2} \
+             {:>10.2} {:>8.2} {:>8.4} {:>10.4} {:>10.4}",
+            value,
+            report.packets_generated(),
+            report.packets_processed(),
+            report.packets_dropped(),
+            report.packets_tail_dropped(),
+            report.collisions(),
+            report.throughput(),
+            report.efficiency() * 100.0,
+            report.offered_load(),
+            report.channel.utilization() * 100.0,
+            report.channel.collision_fraction() * 100.0,
+            report.channel.idle_fraction() * 100.0,
+            report.fairness(),
+            report.sojourn.mean(),
+            report.sojourn.stddev()
+        );
+    }
 }
@@ -0,0 +1,372 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use serde_json::{self, Map, Value};
+use engine::{Params, Report};
+use series::Sample;
+use simulators::ServerStatistics;
+use switch::PortStatistics;
+
+// Format is the format simulation results are written out in. Format::Text is meant to be read,
+// Format::Json and Format::Csv to be ingested by other tools and follow the schema of Record.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "unknown format '{}', expected 'text', 'json' or 'csv'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+            Format::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+// Record is the machine-readable form of a simulation run: its Params and Results.
+#[derive(Serialize)]
+struct Record<'a> {
+    params: &'a Params,
+    results: Results<'a>,
+}
+
+// Results holds the aggregate statistics of a Report, named after the Report methods computing
+// them (packets_port_dropped only for switched LANs), followed by the statistics of every node and
+// the run's time series, if sampled. Sojourn times and end-to-end delays are in seconds,
+// throughput in bits/s and every fraction is in [0, 1].
+#[derive(Serialize)]
+struct Results<'a> {
+    sojourn_mean: f64,
+    sojourn_stddev: f64,
+    delay_mean: f64,
+    delay_stddev: f64,
+    packets_generated: u32,
+    packets_processed: u32,
+    packets_dropped: u32,
+    packets_tail_dropped: u32,
+    packets_received: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    packets_port_dropped: Option<u32>,
+    bits_processed: u64,
+    collisions: u32,
+    throughput: f64,
+    efficiency: f64,
+    offered_load: f64,
+    utilization: f64,
+    collision_fraction: f64,
+    idle_fraction: f64,
+    jam_fraction: f64,
+    wasted_fraction: f64,
+    fairness: f64,
+    nodes: Vec<NodeResults<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    series: &'a [Sample],
+}
+
+// NodeResults holds the statistics of a single node, its ServerStatistics (and those of the switch
+// port it's linked to, if any) along with the sojourn time and throughput of its packets, its
+// share of the overall throughput and the end-to-end delay of the packets it received.
+#[derive(Serialize)]
+struct NodeResults<'a> {
+    #[serde(flatten)]
+    statistics: &'a ServerStatistics,
+    #[serde(flatten)]
+    port: Option<&'a PortStatistics>,
+    sojourn_mean: f64,
+    sojourn_stddev: f64,
+    delay_mean: f64,
+    delay_stddev: f64,
+    throughput: f64,
+    share: f64,
+}
+
+impl<'a> Record<'a> {
+    fn new(params: &'a Params, report: &'a Report) -> Record<'a> {
+        Record {
+            params,
+            results: Results {
+                sojourn_mean: report.sojourn.mean(),
+                sojourn_stddev: report.sojourn.stddev(),
+                delay_mean: report.delay().mean(),
+                delay_stddev: report.delay().stddev(),
+                packets_generated: report.packets_generated(),
+                packets_processed: report.packets_processed(),
+                packets_dropped: report.packets_dropped(),
+                packets_tail_dropped: report.packets_tail_dropped(),
+                packets_received: report.packets_received(),
+                packets_port_dropped: if report.ports.is_empty() {
+                    None
+                } else {
+                    Some(report.packets_port_dropped())
+                },
+                bits_processed: report.bits_processed(),
+                collisions: report.collisions(),
+                throughput: report.throughput(),
+                efficiency: report.efficiency(),
+                offered_load: report.offered_load(),
+                utilization: report.channel.utilization(),
+                collision_fraction: report.channel.collision_fraction(),
+                idle_fraction: report.channel.idle_fraction(),
+                jam_fraction: report.channel.jam_fraction(),
+                wasted_fraction: report.channel.wasted_fraction(),
+                fairness: report.fairness(),
+                nodes: report
+                    .statistics
+                    .iter()
+                    .enumerate()
+                    .map(|(id, statistics)| {
+                        NodeResults {
+                            statistics,
+                            port: report.ports.get(id),
+                            sojourn_mean: statistics.sojourn.mean(),
+                            sojourn_stddev: statistics.sojourn.stddev(),
+                            delay_mean: statistics.delay.mean(),
+                            delay_stddev: statistics.delay.stddev(),
+                            throughput: report.node_throughput(id),
+                            share: report.share(id),
+                        }
+                    })
+                    .collect(),
+                series: &report.series,
+            },
+        }
+    }
+
+    fn value(&self) -> Map<String, Value> {
+        match serde_json::to_value(self).unwrap() {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+}
+
+// json writes every run as a JSON object on a line of its own (JSON Lines).
+pub fn json<W: Write>(w: &mut W, runs: &[(Params, Report)]) -> io::Result<()> {
+    for (params, report) in runs {
+        serde_json::to_writer(&mut *w, &Record::new(params, report))?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+// csv writes every run as a set of rows: one for the aggregate results, the node column set to
+// "all", followed by one per node and then one per sample of the run's time series, if any, the
+// node column set to "series". Columns only found in the aggregate results are left empty for
+// nodes and samples and vice versa. Every row carries the run's Params.
+pub fn csv<W: Write>(w: &mut W, runs: &[(Params, Report)]) -> io::Result<()> {
+    let mut columns: Vec<String> = vec![];
+    for (i, (params, report)) in runs.iter().enumerate() {
+        let record = Record::new(params, report).value();
+        let params = object(&record["params"]);
+        let mut results = object(&record["results"]).clone();
+        let nodes = results.remove("nodes").unwrap();
+        let series = results.remove("series").unwrap_or_else(|| Value::Array(vec![]));
+
+        if i == 0 {
+            columns = results.keys().cloned().collect();
+            for node in nodes.as_array().unwrap().iter().chain(series.as_array().unwrap()) {
+                for k in object(node).keys() {
+                    if !columns.contains(k) {
+                        columns.push(k.clone());
+                    }
+                }
+            }
+            let header: Vec<_> = params
+                .keys()
+                .map(|k| k.as_str())
+                .chain(Some("node"))
+                .chain(columns.iter().map(|k| k.as_str()))
+                .collect();
+            writeln!(w, "{}", header.join(","))?;
+        }
+
+        let row = |node: String, stats: &Map<String, Value>| -> String {
+            let cells: Vec<_> = params
+                .values()
+                .map(cell)
+                .chain(Some(node))
+                .chain(columns.iter().map(
+                    |k| stats.get(k).map(cell).unwrap_or_default(),
+                ))
+                .collect();
+            cells.join(",")
+        };
+        writeln!(w, "{}", row("all".to_string(), &results))?;
+        for (id, node) in nodes.as_array().unwrap().iter().enumerate() {
+            writeln!(w, "{}", row(id.to_string(), object(node)))?;
+        }
+        for sample in series.as_array().unwrap() {
+            writeln!(w, "{}", row("series".to_string(), object(sample)))?;
+        }
+    }
+    Ok(())
+}
+
+fn object(value: &Value) -> &Map<String, Value> {
+    value.as_object().unwrap()
+}
+
+// cell formats a value as a CSV cell. Missing values (null, as NaN serializes) are left empty,
+// array elements separated by semicolons and strings quoted if need be.
+fn cell(value: &Value) -> String {
+    let s = match *value {
+        Value::Null => String::new(),
+        Value::String(ref s) => s.clone(),
+        Value::Array(ref values) => {
+            let cells: Vec<_> = values.iter().map(cell).collect();
+            cells.join(";")
+        }
+        ref v => v.to_string(),
+    };
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::{self, Engine};
+    use sizes::PacketSize;
+    use serde_json::Value;
+
+    fn runs() -> Vec<(Params, Report)> {
+        let params = Params {
+            rate: 50,
+            psize: PacketSize::Constant(1000),
+            duration: 1,
+            ncount: 2,
+            engine: Engine::Event,
+            ..Params::default()
+        };
+        let report = engine::run(&params);
+        vec![(params, report)]
+    }
+
+    #[test]
+    fn json_schema() {
+        let mut buf = vec![];
+        json(&mut buf, &runs()).unwrap();
+        let line = String::from_utf8(buf).unwrap();
+        assert_eq!(line.lines().count(), 1);
+
+        let record: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(record["params"]["rate"], 50);
+        assert_eq!(record["params"]["engine"], "event");
+        assert_eq!(record["params"]["qsize"], Value::Null);
+        let results = &record["results"];
+        assert_eq!(results["nodes"].as_array().unwrap().len(), 2);
+        let generated: u64 = (0..2)
+            .map(|i| results["nodes"][i]["packets_generated"].as_u64().unwrap())
+            .sum();
+        assert_eq!(results["packets_generated"].as_u64().unwrap(), generated);
+        let share: f64 = (0..2)
+            .map(|i| results["nodes"][i]["share"].as_f64().unwrap())
+            .sum();
+        assert!((share - 1.0).abs() < 1e-9);
+        assert!(results["nodes"][0]["retries"].is_array());
+        assert!(results["fairness"].as_f64().unwrap() <= 1.0);
+    }
+
+    #[test]
+    fn csv_schema() {
+        let mut buf = vec![];
+        csv(&mut buf, &runs()).unwrap();
+        let out = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+
+        let header: Vec<_> = lines[0].split(',').collect();
+        assert_eq!(&header[..2], &["rate", "psize"]);
+        let node = header.iter().position(|&c| c == "node").unwrap();
+        let utilization = header.iter().position(|&c| c == "utilization").unwrap();
+        let share = header.iter().position(|&c| c == "share").unwrap();
+        for (i, line) in lines[1..].iter().enumerate() {
+            let row: Vec<_> = line.split(',').collect();
+            assert_eq!(row.len(), header.len());
+            if i == 0 {
+                assert_eq!(row[node], "all");
+                assert!(!row[utilization].is_empty());
+            } else {
+                assert_eq!(row[node], (i - 1).to_string());
+                assert!(row[utilization].is_empty());
+                assert!(!row[share].is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn series_schema() {
+        let mut runs = runs();
+        assert!(!json_string(&runs).contains("series"));
+        runs[0].0.sample = Some(0.5);
+        runs[0].1 = engine::run(&runs[0].0);
+
+        let record: Value = serde_json::from_str(&json_string(&runs)).unwrap();
+        let series = record["results"]["series"].as_array().unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[1]["time"], 1.0);
+        assert_eq!(series[1]["queues"].as_array().unwrap().len(), 2);
+
+        let mut buf = vec![];
+        csv(&mut buf, &runs).unwrap();
+        let out = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 6);
+        let header: Vec<_> = lines[0].split(',').collect();
+        let node = header.iter().position(|&c| c == "node").unwrap();
+        let time = header.iter().position(|&c| c == "time").unwrap();
+        let busy = header.iter().position(|&c| c == "busy").unwrap();
+        for line in &lines[4..] {
+            let row: Vec<_> = line.split(',').collect();
+            assert_eq!(row.len(), header.len());
+            assert_eq!(row[node], "series");
+            assert!(!row[time].is_empty() && !row[busy].is_empty());
+        }
+    }
+
+    #[test]
+    fn switch_schema() {
+        let mut runs = runs();
+        assert!(!json_string(&runs).contains("port"));
+        runs[0].0.lan = engine::Lan::Switch;
+        runs[0].0.engine = Engine::Tick;
+        runs[0].1 = engine::run(&runs[0].0);
+
+        let record: Value = serde_json::from_str(&json_string(&runs)).unwrap();
+        let results = &record["results"];
+        assert_eq!(record["params"]["lan"], "switch");
+        assert_eq!(results["packets_port_dropped"], 0);
+        let forwarded: u64 = (0..2)
+            .map(|i| results["nodes"][i]["packets_forwarded"].as_u64().unwrap())
+            .sum();
+        assert!(forwarded > 0);
+    }
+
+    fn json_string(runs: &[(Params, Report)]) -> String {
+        let mut buf = vec![];
+        json(&mut buf, runs).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
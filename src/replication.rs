@@ -0,0 +1,311 @@
+use std::f64::consts::PI;
+use std::thread;
+use engine::{self, Params, Report};
+
+// Metric computes a single figure of a simulation run.
+pub type Metric = fn(&Report) -> f64;
+
+// METRICS are the per-run metrics estimated across replications, named as they are in
+// output::Results.
+pub const METRICS: &[(&str, Metric)] = &[
+    ("sojourn_mean", sojourn_mean),
+    ("packets_processed", packets_processed),
+    ("packets_dropped", packets_dropped),
+    ("collisions", collisions),
+    ("throughput", Report::throughput),
+    ("efficiency", Report::efficiency),
+    ("utilization", utilization),
+    ("collision_fraction", collision_fraction),
+    ("idle_fraction", idle_fraction),
+    ("jam_fraction", jam_fraction),
+    ("wasted_fraction", wasted_fraction),
+    ("fairness", Report::fairness),
+    ("delay_mean", delay_mean),
+    ("offered_load", Report::offered_load),
+];
+
+fn sojourn_mean(report: &Report) -> f64 {
+    report.sojourn.mean()
+}
+
+fn delay_mean(report: &Report) -> f64 {
+    report.delay().mean()
+}
+
+fn packets_processed(report: &Report) -> f64 {
+    f64::from(report.packets_processed())
+}
+
+fn packets_dropped(report: &Report) -> f64 {
+    f64::from(report.packets_dropped())
+}
+
+fn collisions(report: &Report) -> f64 {
+    f64::from(report.collisions())
+}
+
+fn utilization(report: &Report) -> f64 {
+    report.channel.utilization()
+}
+
+fn collision_fraction(report: &Report) -> f64 {
+    report.channel.collision_fraction()
+}
+
+fn idle_fraction(report: &Report) -> f64 {
+    report.channel.idle_fraction()
+}
+
+fn jam_fraction(report: &Report) -> f64 {
+    report.channel.jam_fraction()
+}
+
+fn wasted_fraction(report: &Report) -> f64 {
+    report.channel.wasted_fraction()
+}
+
+// replicas returns the Params of every replication of params, the r-th seeded with
+// params.seed + r and otherwise identical. As every RNG stream is derived from the seed (see
+// rng::stream), replications are independent of one another.
+pub fn replicas(params: &Params) -> Vec<Params> {
+    (0..params.replications)
+        .map(|r| {
+            Params {
+                seed: params.seed.wrapping_add(u64::from(r)),
+                ..params.clone()
+            }
+        })
+        .collect()
+}
+
+// run simulates every one of the given Params, spreading them across the given number of
+// threads, and returns their Reports in order.
+pub fn run(params: Vec<Params>, threads: usize) -> Vec<(Params, Report)> {
+    if params.is_empty() {
+        return vec![];
+    }
+    let threads = threads.max(1).min(params.len());
+    let chunk = params.len().div_ceil(threads);
+    let handles: Vec<_> = params
+        .chunks(chunk)
+        .map(|chunk| {
+            let chunk = chunk.to_vec();
+            thread::spawn(move || {
+                chunk
+                    .into_iter()
+                    .map(|params| {
+                        let report = engine::run(&params);
+                        (params, report)
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect()
+}
+
+// Estimate is the sample mean of a metric across replications along with the half-width of its
+// confidence interval, derived from Student's t-distribution. The half-width is NaN with fewer
+// than two replications.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Estimate {
+    pub mean: f64,
+    pub half_width: f64,
+}
+
+impl Estimate {
+    // Estimate::new returns the Estimate of the mean of the samples at the given confidence
+    // level.
+    pub fn new(samples: &[f64], level: f64) -> Estimate {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        if samples.len() < 2 {
+            return Estimate {
+                mean,
+                half_width: f64::NAN,
+            };
+        }
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        Estimate {
+            mean,
+            half_width: t_quantile((1.0 + level) / 2.0, n - 1.0) * (variance / n).sqrt(),
+        }
+    }
+}
+
+// estimates returns the Estimate of every one of METRICS across the given replications.
+pub fn estimates(runs: &[(Params, Report)], level: f64) -> Vec<(&'static str, Estimate)> {
+    METRICS
+        .iter()
+        .map(|&(name, metric)| {
+            let samples: Vec<_> = runs.iter().map(|(_, report)| metric(report)).collect();
+            (name, Estimate::new(&samples, level))
+        })
+        .collect()
+}
+
+// t_quantile returns the p-quantile, p in (0.5, 1), of Student's t-distribution with df degrees of
+// freedom, found by bisection over its CDF.
+fn t_quantile(p: f64, df: f64) -> f64 {
+    let mut hi = 1.0;
+    while t_cdf(hi, df) < p {
+        hi *= 2.0;
+    }
+    let mut lo = 0.0;
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if t_cdf(mid, df) < p {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+// t_cdf returns the CDF of Student's t-distribution with df degrees of freedom at t >= 0.
+fn t_cdf(t: f64, df: f64) -> f64 {
+    1.0 - 0.5 * incomplete_beta(df / (df + t * t), df / 2.0, 0.5)
+}
+
+// incomplete_beta returns the regularized incomplete beta function I_x(a, b), evaluated with its
+// continued fraction expansion (Numerical Recipes, 6.4).
+fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln())
+        .exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_fraction(1.0 - x, b, a) / b
+    }
+}
+
+fn beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = f64::from(m);
+        for &num in &[
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ]
+        {
+            d = 1.0 + num * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + num / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    h
+}
+
+// ln_gamma returns the natural logarithm of the gamma function, using the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::Engine;
+    use sizes::PacketSize;
+
+    #[test]
+    fn t_quantiles() {
+        for &(p, df, t) in &[
+            (0.975, 1.0, 12.7062),
+            (0.975, 9.0, 2.2622),
+            (0.95, 4.0, 2.1318),
+            (0.995, 30.0, 2.7500),
+        ]
+        {
+            assert!((t_quantile(p, df) - t).abs() < 1e-4, "t({}, {})", p, df);
+        }
+    }
+
+    #[test]
+    fn estimate() {
+        let estimate = Estimate::new(&[1.0, 2.0, 3.0, 4.0, 5.0], 0.95);
+        assert_eq!(estimate.mean, 3.0);
+        // t(0.975, 4) * sqrt(2.5 / 5)
+        assert!((estimate.half_width - 1.9632).abs() < 1e-4);
+        assert!(Estimate::new(&[1.0], 0.95).half_width.is_nan());
+    }
+
+    #[test]
+    fn replications() {
+        let params = Params {
+            rate: 50,
+            psize: PacketSize::Constant(1000),
+            duration: 1,
+            ncount: 3,
+            seed: 7,
+            engine: Engine::Event,
+            replications: 4,
+            ..Params::default()
+        };
+        let replicas = replicas(&params);
+        let seeds: Vec<_> = replicas.iter().map(|p| p.seed).collect();
+        assert_eq!(seeds, vec![7, 8, 9, 10]);
+
+        // The results are independent of the number of threads they're spread across.
+        let serial = run(replicas.clone(), 1);
+        let parallel = run(replicas, 3);
+        assert_eq!(serial.len(), 4);
+        for ((_, s), (_, p)) in serial.iter().zip(&parallel) {
+            assert_eq!(s.packets_processed(), p.packets_processed());
+            assert_eq!(s.sojourn.mean(), p.sojourn.mean());
+        }
+
+        let estimates = estimates(&serial, 0.95);
+        let &(name, throughput) = &estimates[4];
+        assert_eq!(name, "throughput");
+        assert!(throughput.half_width > 0.0);
+        assert!(throughput.half_width < throughput.mean);
+    }
+}
@@ -0,0 +1,35 @@
+use rand::{SeedableRng, StdRng};
+
+// Stream identifies one of the independent random number streams a node draws from. Keeping
+// traffic generation and medium access on separate streams means the packets a node generates do
+// not depend on how many times it happened to back off.
+#[derive(Clone, Copy, Debug)]
+pub enum Stream {
+    Traffic,
+    Mac,
+}
+
+// rng::stream derives the StdRng for the given node and stream from the master seed. The same
+// (seed, id, stream) triple always yields the same sequence, independent of the number of nodes
+// simulated.
+pub fn stream(seed: u64, id: usize, stream: Stream) -> StdRng {
+    StdRng::from_seed(&[(seed >> 32) as usize, seed as usize, id, stream as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stream, Stream};
+    use rand::Rng;
+
+    #[test]
+    fn streams_are_reproducible() {
+        let a: Vec<u32> = stream(42, 0, Stream::Traffic).gen_iter().take(5).collect();
+        let b: Vec<u32> = stream(42, 0, Stream::Traffic).gen_iter().take(5).collect();
+        assert_eq!(a, b);
+
+        let c: Vec<u32> = stream(42, 1, Stream::Traffic).gen_iter().take(5).collect();
+        let d: Vec<u32> = stream(42, 0, Stream::Mac).gen_iter().take(5).collect();
+        let e: Vec<u32> = stream(43, 0, Stream::Traffic).gen_iter().take(5).collect();
+        assert!(a != c && a != d && a != e);
+    }
+}
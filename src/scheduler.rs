@@ -0,0 +1,630 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use rand::{Rng, StdRng};
+use stats::OnlineStats;
+use engine::{Params, Report};
+use generators::Generator;
+use rng::{self, Stream};
+use series::Sampler;
+use backoff::Backoff;
+use log::{self, EventLog};
+use simulators::{ChannelStatistics, Client, CsmaMode, Packet, Queue, ServerStatistics, Timing};
+
+// EventKind enumerates what can happen to a node at a given instant. Events scheduled for the same
+// tick are processed in the order the variants are declared: in the tick engine a Server only
+// observes signals written to the Medium in prior ticks and its Client generates packets before
+// the Server acts on them, so signal propagation comes first, then packet arrivals, and then node
+// state transitions.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum EventKind {
+    // Another node's signal starts (CarrierOn) or stops (CarrierOff) reaching this node.
+    CarrierOn,
+    CarrierOff,
+    // The last bit of a packet addressed to the node reached it.
+    Reception,
+    // The node's Client generates a packet.
+    Arrival,
+    // The node detected another signal while transmitting.
+    Collision,
+    // The node finished jamming the medium.
+    JamDone,
+    // The node finished sensing the medium.
+    SensingDone,
+    // The node pushed the last bit of its packet onto the medium.
+    TransmissionDone,
+    // The node finished backing off.
+    BackoffExpiry,
+    // The node is ready to dequeue its next packet.
+    Dequeue,
+}
+
+// Event is a timestamped EventKind for a given node. Events are ordered by time, kind, and then
+// by the order in which they were scheduled. The tag disambiguates events that may have been
+// invalidated since being scheduled, for Collision and TransmissionDone it is the tick the
+// transmission in question started at. For Reception it identifies the packet received (see
+// Scheduler.frames).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct Event {
+    time: u32,
+    kind: EventKind,
+    seq: u64,
+    node: usize,
+    tag: u32,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum NodeState {
+    Idle,
+    Sensing {
+        until: u32,
+        busy: bool,
+        current_packet: Packet,
+        retries: u32,
+    },
+    Transmitting {
+        start: u32,
+        current_packet: Packet,
+        retries: u32,
+    },
+    Jamming {
+        current_packet: Packet,
+        retries: u32,
+    },
+    Waiting {
+        current_packet: Packet,
+        retries: u32,
+    },
+}
+
+// Node is the event driven counterpart of simulators::Server. Instead of reading the Medium every
+// tick, it keeps count of the number of signals currently reaching it.
+struct Node<G: Generator> {
+    client: Client<G>,
+    queue: Queue,
+    statistics: ServerStatistics,
+    state: NodeState,
+    csma: CsmaMode,
+    backoff: Box<dyn Backoff>,
+    rng: StdRng,
+    carrier: u32,
+}
+
+// Scheduler is a discrete-event simulator of the CSMA/CD LAN. It maintains a priority queue of
+// timestamped events and jumps from one to the next, as opposed to walking every tick of the
+// simulation. The semantics (sensing period, backoff, collision detection, propagation delay) are
+// those of simulators::Server and simulators::Medium, and nodes draw from the same RNG streams.
+// delays[i][j] is the number of ticks it takes the signal of node j to reach node i.
+//
+// Packets in flight to the nodes they're addressed to are kept in frames, keyed by the tag of
+// their Reception events, along with the number of those yet to happen.
+//
+// The number of nodes transmitting, and of those jamming, at any given time is tracked as a set of
+// changes, keyed by the tick they take effect at, tallied into ChannelStatistics (and sampled and
+// logged, if need be) at the end of the simulation.
+pub struct Scheduler<G: Generator> {
+    nodes: Vec<Node<G>>,
+    delays: Vec<Vec<u32>>,
+    events: BinaryHeap<Reverse<Event>>,
+    seq: u64,
+    now: u32,
+    ticks: u32,
+    warmup: u32,
+    timing: Timing,
+    attempts: u32,
+    sojourn: OnlineStats,
+    transmitters: BTreeMap<u32, (i32, i32)>,
+    frames: HashMap<u32, (Packet, usize)>,
+    frame: u32,
+    sampler: Option<Sampler>,
+    log: Option<EventLog>,
+}
+
+impl Scheduler<Box<dyn Generator>> {
+    // Scheduler::new returns a Scheduler for the LAN described by params.
+    pub fn new(params: &Params) -> Self {
+        let timing = params.timing();
+        let nodes = (0..params.ncount)
+            .map(|id| {
+                let node = params.node(id);
+                Node {
+                    client: Client::new(
+                        node.traffic.build(f64::from(node.rate), id),
+                        rng::stream(params.seed, id, Stream::Traffic),
+                        timing.resolution,
+                        node.psize.build(params.ethernet),
+                    ).with_destinations(id, params.ncount, node.dest),
+                    queue: Queue::new(node.qsize),
+                    csma: node.csma,
+                    statistics: ServerStatistics::new(),
+                    state: NodeState::Idle,
+                    backoff: params.backoff_policy().build(params.truncation),
+                    rng: rng::stream(params.seed, id, Stream::Mac),
+                    carrier: 0,
+                }
+            })
+            .collect();
+        Scheduler {
+            nodes,
+            delays: params.topology().delays(&timing),
+            events: BinaryHeap::new(),
+            seq: 0,
+            now: 0,
+            ticks: params.ticks(),
+            warmup: params.warmup_ticks(),
+            timing,
+            attempts: params.attempts,
+            sojourn: OnlineStats::new(),
+            transmitters: BTreeMap::new(),
+            frames: HashMap::new(),
+            frame: 0,
+            sampler: params.sample.map(
+                |interval| Sampler::new(interval, params.ticks(), timing),
+            ),
+            log: params.log.as_ref().map(|filter| EventLog::new(filter, params.resolution)),
+        }
+    }
+}
+
+impl<G: Generator> Scheduler<G> {
+    // Scheduler.run processes events in order until the end of the simulation.
+    pub fn run(mut self) -> Report {
+        for id in 0..self.nodes.len() {
+            let next = self.nodes[id].client.skip();
+            self.schedule(next, EventKind::Arrival, id, 0);
+        }
+
+        while let Some(Reverse(event)) = self.events.pop() {
+            if self.now < self.warmup && event.time >= self.warmup {
+                self.reset_statistics();
+            }
+            self.sample_queues(event.time);
+            self.now = event.time;
+            let id = event.node;
+            match event.kind {
+                EventKind::CarrierOn => self.carrier_on(id),
+                EventKind::CarrierOff => self.nodes[id].carrier -= 1,
+                EventKind::Reception => self.reception(id, event.tag),
+                EventKind::Arrival => self.arrival(id),
+                EventKind::Collision => {
+                    if let NodeState::Transmitting {
+                        start,
+                        current_packet,
+                        retries,
+                    } = self.nodes[id].state
+                    {
+                        if start == event.tag {
+                            // The node stops transmitting its packet and jams the medium in its
+                            // stead, its signal carrying on uninterrupted.
+                            self.nodes[id].statistics.collisions += 1;
+                            self.sample_collision();
+                            self.log(id, log::Kind::Collision, &current_packet, retries);
+                            self.jam(id, current_packet, retries);
+                        }
+                    }
+                }
+                EventKind::JamDone => {
+                    if let NodeState::Jamming {
+                        current_packet,
+                        retries,
+                    } = self.nodes[id].state
+                    {
+                        self.backoff_or_drop(id, current_packet, retries + 1);
+                    }
+                }
+                EventKind::SensingDone => {
+                    if let NodeState::Sensing {
+                        busy,
+                        current_packet,
+                        retries,
+                        ..
+                    } = self.nodes[id].state
+                    {
+                        if busy && self.nodes[id].csma != CsmaMode::NonPersistent {
+                            // Persistent modes keep sensing until the medium is idle.
+                            self.sense(id, current_packet, retries);
+                        } else if busy {
+                            self.backoff_or_drop(id, current_packet, retries + 1);
+                        } else if self.defers(id) {
+                            self.log(id, log::Kind::Deferred, &current_packet, retries);
+                            let slot = self.timing.slot;
+                            self.wait(id, current_packet, retries, slot);
+                        } else {
+                            self.transmit(id, current_packet, retries);
+                        }
+                    }
+                }
+                EventKind::TransmissionDone => {
+                    if let NodeState::Transmitting {
+                        start,
+                        current_packet,
+                        retries,
+                    } = self.nodes[id].state
+                    {
+                        if start == event.tag {
+                            self.transmitted(id, current_packet, retries);
+                        }
+                    }
+                }
+                EventKind::BackoffExpiry => {
+                    if let NodeState::Waiting {
+                        current_packet,
+                        retries,
+                    } = self.nodes[id].state
+                    {
+                        self.sense(id, current_packet, retries);
+                    }
+                }
+                EventKind::Dequeue => {
+                    if self.nodes[id].state == NodeState::Idle {
+                        self.dequeue(id);
+                    }
+                }
+            }
+        }
+
+        if self.now < self.warmup {
+            self.reset_statistics();
+        }
+        let ticks = self.ticks;
+        self.sample_queues(ticks);
+
+        // Only the ticks past the warm-up period are recorded.
+        let mut channel = ChannelStatistics::new();
+        let (mut last, mut transmitters, mut jammers) = (self.warmup, 0, 0);
+        for (&time, &(transmitters_delta, jammers_delta)) in self.transmitters.range(..self.ticks) {
+            if time > last {
+                channel.record(
+                    transmitters as usize,
+                    jammers as usize,
+                    u64::from(time - last),
+                );
+                last = time;
+            }
+            transmitters += transmitters_delta;
+            jammers += jammers_delta;
+        }
+        channel.record(
+            transmitters as usize,
+            jammers as usize,
+            u64::from(self.ticks - last),
+        );
+
+        let series = self.sampler.take().map(|mut sampler| {
+            let (mut last, mut transmitters) = (0, 0);
+            for (&time, &(transmitters_delta, _)) in self.transmitters.range(..self.ticks) {
+                if transmitters > 0 {
+                    sampler.busy(last, time);
+                }
+                last = time;
+                transmitters += transmitters_delta;
+            }
+            if transmitters > 0 {
+                sampler.busy(last, self.ticks);
+            }
+            sampler.samples()
+        });
+        let log = self.log.take().map(|mut log| {
+            // The channel's state changes as of every tick where the number of nodes transmitting
+            // or jamming does, as it would in simulators::Medium.
+            let (mut state, mut transmitters, mut jammers) = (log::Kind::ChannelIdle, 0, 0);
+            for (&time, &(transmitters_delta, jammers_delta)) in
+                self.transmitters.range(..self.ticks)
+            {
+                transmitters += transmitters_delta;
+                jammers += jammers_delta;
+                let channel = log::Kind::channel(transmitters as usize, jammers as usize);
+                if channel != state {
+                    log.channel(time, channel);
+                    state = channel;
+                }
+            }
+            log::merge(vec![log])
+        });
+
+        Report {
+            sojourn: self.sojourn,
+            statistics: self.nodes.into_iter().map(|n| n.statistics).collect(),
+            channel,
+            duration: self.timing.seconds(self.ticks - self.warmup),
+            lspeed: self.timing.lspeed,
+            series: series.unwrap_or_default(),
+            log: log.unwrap_or_default(),
+            ports: vec![],
+        }
+    }
+
+    // Scheduler.reset_statistics discards the statistics gathered thus far, at the end of the
+    // warm-up period.
+    fn reset_statistics(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.statistics = ServerStatistics::new();
+        }
+        self.sojourn = OnlineStats::new();
+    }
+
+    // Scheduler.sample_queues snapshots the length of every node's queue for every sample due by
+    // the given tick, the queues standing as they did at the end of the tick prior.
+    fn sample_queues(&mut self, time: u32) {
+        if let Some(ref mut sampler) = self.sampler {
+            while sampler.next().is_some_and(|next| next <= time) {
+                sampler.queues(self.nodes.iter().map(|n| n.queue.len()).collect());
+            }
+        }
+    }
+
+    // Scheduler.schedule enqueues an event, discarding it if it falls past the end of the
+    // simulation.
+    fn schedule(&mut self, time: u32, kind: EventKind, node: usize, tag: u32) {
+        if time >= self.ticks {
+            return;
+        }
+        self.seq += 1;
+        self.events.push(Reverse(Event {
+            time,
+            kind,
+            seq: self.seq,
+            node,
+            tag,
+        }));
+    }
+
+    // Scheduler.broadcast schedules the given carrier event at every node other than the sender,
+    // as the sender's signal starts or stops at the given time, at which point it takes the
+    // propagation delay between the two to reach the node.
+    fn broadcast(&mut self, sender: usize, time: u32, kind: EventKind) {
+        for id in 0..self.nodes.len() {
+            if id != sender {
+                let delay = self.delays[id][sender];
+                self.schedule(time + delay, kind, id, sender as u32);
+            }
+        }
+    }
+
+    fn arrival(&mut self, id: usize) {
+        let now = self.now;
+        let packet = self.nodes[id].client.tick(now).unwrap();
+        self.nodes[id].statistics.packets_generated += 1;
+        self.log(id, log::Kind::Generated, &packet, 0);
+        if !self.nodes[id].queue.push(packet) {
+            self.nodes[id].statistics.packets_tail_dropped += 1;
+            self.log(id, log::Kind::TailDropped, &packet, 0);
+        }
+        let next = (now + 1).saturating_add(self.nodes[id].client.skip());
+        self.schedule(next, EventKind::Arrival, id, 0);
+
+        if self.nodes[id].state == NodeState::Idle {
+            self.dequeue(id);
+        }
+    }
+
+    fn carrier_on(&mut self, id: usize) {
+        let now = self.now;
+        let node = &mut self.nodes[id];
+        node.carrier += 1;
+        match node.state {
+            NodeState::Sensing {
+                until,
+                current_packet,
+                retries,
+                ..
+            } if now < until => {
+                node.state = NodeState::Sensing {
+                    until,
+                    busy: true,
+                    current_packet,
+                    retries,
+                };
+            }
+            NodeState::Transmitting { start, .. } => {
+                // Defer to a Collision event so as to observe all other carrier events this tick.
+                self.schedule(now, EventKind::Collision, id, start);
+            }
+            _ => {}
+        }
+    }
+
+    fn dequeue(&mut self, id: usize) {
+        match self.nodes[id].queue.pop() {
+            Some(packet) => self.sense(id, packet, 0),
+            None => self.nodes[id].state = NodeState::Idle,
+        }
+    }
+
+    fn sense(&mut self, id: usize, packet: Packet, retries: u32) {
+        self.log(id, log::Kind::Sensing, &packet, retries);
+        let until = self.now + self.timing.sensing;
+        self.nodes[id].state = NodeState::Sensing {
+            until,
+            busy: self.nodes[id].carrier > 0,
+            current_packet: packet,
+            retries,
+        };
+        self.schedule(until, EventKind::SensingDone, id, 0);
+    }
+
+    fn transmit(&mut self, id: usize, packet: Packet, retries: u32) {
+        self.log(id, log::Kind::Transmitting, &packet, retries);
+        if self.nodes[id].carrier > 0 {
+            // Collision on the very first bit, the node jams the medium straight away.
+            let now = self.now;
+            self.broadcast(id, now, EventKind::CarrierOn);
+            self.signal(now, 1, 0);
+            self.nodes[id].statistics.collisions += 1;
+            self.sample_collision();
+            self.log(id, log::Kind::Collision, &packet, retries);
+            self.jam(id, packet, retries);
+            return;
+        }
+
+        let now = self.now;
+        let ticks = (f64::from(packet.length) / self.timing.bits_per_tick()).ceil() as u32;
+        self.nodes[id].state = NodeState::Transmitting {
+            start: now,
+            current_packet: packet,
+            retries,
+        };
+        self.broadcast(id, now, EventKind::CarrierOn);
+        self.signal(now, 1, 0);
+        self.schedule(
+            now + ticks.max(1) - 1,
+            EventKind::TransmissionDone,
+            id,
+            now,
+        );
+    }
+
+    fn transmitted(&mut self, id: usize, packet: Packet, retries: u32) {
+        let now = self.now;
+        let sojourn = self.timing.seconds(now - packet.time_generated);
+        self.sojourn.add(sojourn);
+        self.nodes[id].statistics.processed(packet.length, sojourn, retries);
+        self.log(id, log::Kind::Transmitted, &packet, retries);
+        if let Some(ref mut sampler) = self.sampler {
+            sampler.processed(now, packet.length);
+        }
+        self.broadcast(id, now + 1, EventKind::CarrierOff);
+        self.signal(now + 1, -1, 0);
+
+        // The packet reaches every node it's addressed to as its last bit does.
+        let receivers: Vec<_> = (0..self.nodes.len())
+            .filter(|&r| {
+                packet.destination.reaches(id, r) && now + self.delays[r][id] < self.ticks
+            })
+            .collect();
+        if !receivers.is_empty() {
+            self.frame = self.frame.wrapping_add(1);
+            let frame = self.frame;
+            self.frames.insert(frame, (packet, receivers.len()));
+            for r in receivers {
+                let delay = self.delays[r][id];
+                self.schedule(now + delay, EventKind::Reception, r, frame);
+            }
+        }
+
+        // As with the Server, the next packet is only dequeued on the following tick.
+        self.nodes[id].state = NodeState::Idle;
+        if !self.nodes[id].queue.is_empty() {
+            self.schedule(now + 1, EventKind::Dequeue, id, 0);
+        }
+    }
+
+    // Scheduler.log is the counterpart of Server.log.
+    fn log(&mut self, id: usize, kind: log::Kind, packet: &Packet, retries: u32) {
+        if let Some(ref mut log) = self.log {
+            log.node(self.now, id, kind, packet, retries);
+        }
+    }
+
+    // Scheduler.sample_collision records a collision detected at the current tick.
+    fn sample_collision(&mut self) {
+        if let Some(ref mut sampler) = self.sampler {
+            sampler.collision(self.now);
+        }
+    }
+
+    // Scheduler.reception has the node receive the packet in flight identified by frame.
+    fn reception(&mut self, id: usize, frame: u32) {
+        let packet = {
+            let &mut (packet, ref mut pending) = self.frames.get_mut(&frame).unwrap();
+            *pending -= 1;
+            packet
+        };
+        if self.frames[&frame].1 == 0 {
+            self.frames.remove(&frame);
+        }
+        let delay = self.timing.seconds(self.now - packet.time_generated);
+        self.nodes[id].statistics.received(packet.length, delay);
+        if let Some(ref mut log) = self.log {
+            log.received(self.now, id, &packet);
+        }
+    }
+
+    // Scheduler.defers is the counterpart of Server.defers.
+    fn defers(&mut self, id: usize) -> bool {
+        match self.nodes[id].csma {
+            CsmaMode::PPersistent(p) => self.nodes[id].rng.gen::<f64>() >= p,
+            _ => false,
+        }
+    }
+
+    // Scheduler.jam has the node, already transmitting, jam the medium for the duration of the
+    // jam signal.
+    fn jam(&mut self, id: usize, packet: Packet, retries: u32) {
+        let now = self.now;
+        let until = now + self.timing.jam;
+        self.nodes[id].state = NodeState::Jamming {
+            current_packet: packet,
+            retries,
+        };
+        self.signal(now, 0, 1);
+        self.signal(until, -1, -1);
+        self.broadcast(id, until, EventKind::CarrierOff);
+        self.schedule(until, EventKind::JamDone, id, 0);
+    }
+
+    // Scheduler.signal records a change in the number of nodes transmitting and jamming, taking
+    // effect at the given tick.
+    fn signal(&mut self, time: u32, transmitters: i32, jammers: i32) {
+        let delta = self.transmitters.entry(time).or_insert((0, 0));
+        delta.0 += transmitters;
+        delta.1 += jammers;
+    }
+
+    // Scheduler.backoff_or_drop is the counterpart of Server.backoff_or_drop.
+    fn backoff_or_drop(&mut self, id: usize, packet: Packet, retries: u32) {
+        if retries >= self.attempts {
+            self.nodes[id].statistics.packets_dropped += 1;
+            self.log(id, log::Kind::Dropped, &packet, retries);
+            self.nodes[id].state = NodeState::Idle;
+            self.dequeue(id);
+            return;
+        }
+
+        let wait_time = {
+            let node = &mut self.nodes[id];
+            node.backoff.slots(&mut node.rng, retries).saturating_mul(self.timing.slot)
+        };
+        self.log(id, log::Kind::Backoff, &packet, retries);
+        self.wait(id, packet, retries, wait_time);
+    }
+
+    fn wait(&mut self, id: usize, packet: Packet, retries: u32, wait_time: u32) {
+        if wait_time == 0 {
+            self.sense(id, packet, retries);
+        } else {
+            self.nodes[id].state = NodeState::Waiting {
+                current_packet: packet,
+                retries,
+            };
+            let now = self.now;
+            self.schedule(now.saturating_add(wait_time), EventKind::BackoffExpiry, id, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_ordering() {
+        let event = |time, kind, seq| {
+            Event {
+                time,
+                kind,
+                seq,
+                node: 0,
+                tag: 0,
+            }
+        };
+        let mut events = BinaryHeap::new();
+        events.push(Reverse(event(2, EventKind::CarrierOn, 1)));
+        events.push(Reverse(event(1, EventKind::SensingDone, 2)));
+        events.push(Reverse(event(1, EventKind::Arrival, 3)));
+        events.push(Reverse(event(1, EventKind::CarrierOff, 4)));
+        events.push(Reverse(event(1, EventKind::Arrival, 5)));
+
+        let order: Vec<_> = (0..5).map(|_| events.pop().unwrap().0.seq).collect();
+        assert_eq!(order, vec![4, 3, 5, 2, 1]);
+    }
+}
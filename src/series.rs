@@ -0,0 +1,186 @@
+use simulators::Timing;
+
+// Sample is a snapshot of the LAN at a given time, in seconds, taken at the end of every sampling
+// interval: the number of packets queued at every node, the throughput thus far (in bits/s), the
+// number of collisions detected over the interval and the fraction of it the channel was busy,
+// whether with a transmission, colliding ones or a jam signal. Samples span the whole run,
+// warm-up period included.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Sample {
+    pub time: f64,
+    pub queues: Vec<usize>,
+    pub throughput: f64,
+    pub collisions: u32,
+    pub busy: f64,
+}
+
+// Sampler accumulates the Samples of a run of the given number of ticks, split into intervals of
+// the given number of ticks (the last one possibly shorter). Every interval is sampled as of the
+// tick it ends at, before the events at that tick.
+pub struct Sampler {
+    interval: u32,
+    ticks: u32,
+    timing: Timing,
+    queues: Vec<Vec<usize>>,
+    bits: Vec<u64>,
+    collisions: Vec<u32>,
+    busy: Vec<u32>,
+}
+
+impl Sampler {
+    // Sampler::new returns a Sampler for a run of the given number of ticks, sampled every
+    // interval seconds.
+    pub fn new(interval: f64, ticks: u32, timing: Timing) -> Sampler {
+        let interval = timing.ticks(interval).max(1);
+        let samples = ticks.div_ceil(interval) as usize;
+        Sampler {
+            interval,
+            ticks,
+            timing,
+            queues: vec![],
+            bits: vec![0; samples],
+            collisions: vec![0; samples],
+            busy: vec![0; samples],
+        }
+    }
+
+    // Sampler.next returns the tick at which the next snapshot of the queues is due, if any.
+    pub fn next(&self) -> Option<u32> {
+        let next = (self.queues.len() as u32 + 1) * self.interval;
+        if self.queues.len() < self.bits.len() {
+            Some(next.min(self.ticks))
+        } else {
+            None
+        }
+    }
+
+    // Sampler.queues records the length of every node's queue as of the tick returned by
+    // Sampler.next.
+    pub fn queues(&mut self, queues: Vec<usize>) {
+        assert!(self.next().is_some());
+        self.queues.push(queues);
+    }
+
+    // Sampler.processed records a packet of the given length being processed at the given tick.
+    pub fn processed(&mut self, tick: u32, length: u32) {
+        self.bits[(tick / self.interval) as usize] += u64::from(length);
+    }
+
+    // Sampler.collision records a collision detected at the given tick.
+    pub fn collision(&mut self, tick: u32) {
+        self.collisions[(tick / self.interval) as usize] += 1;
+    }
+
+    // Sampler.busy records the channel being busy over the ticks [from, to).
+    pub fn busy(&mut self, from: u32, to: u32) {
+        let mut tick = from;
+        while tick < to {
+            let end = ((tick / self.interval + 1) * self.interval).min(to);
+            self.busy[(tick / self.interval) as usize] += end - tick;
+            tick = end;
+        }
+    }
+
+    // Sampler.samples returns the Samples of every interval.
+    pub fn samples(self) -> Vec<Sample> {
+        let Sampler {
+            interval,
+            ticks,
+            timing,
+            queues,
+            bits,
+            collisions,
+            busy,
+        } = self;
+        let (mut processed, mut start) = (0, 0);
+        queues
+            .into_iter()
+            .enumerate()
+            .map(|(i, queues)| {
+                let end = ((i as u32 + 1) * interval).min(ticks);
+                processed += bits[i];
+                let sample = Sample {
+                    time: timing.seconds(end),
+                    queues,
+                    throughput: processed as f64 / timing.seconds(end),
+                    collisions: collisions[i],
+                    busy: f64::from(busy[i]) / f64::from(end - start),
+                };
+                start = end;
+                sample
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::{self, Params};
+    use sizes::PacketSize;
+
+    #[test]
+    fn sampling() {
+        // 25 ticks of a second each, sampled every 10.
+        let mut sampler = Sampler::new(10.0, 25, Timing::new(1.0, 1000.0));
+        sampler.busy(5, 22);
+        sampler.processed(9, 1000);
+        sampler.processed(20, 500);
+        sampler.collision(12);
+        sampler.collision(24);
+        let mut snapshots = vec![];
+        while let Some(tick) = sampler.next() {
+            snapshots.push(tick);
+            sampler.queues(vec![tick as usize]);
+        }
+        assert_eq!(snapshots, vec![10, 20, 25]);
+
+        let samples = sampler.samples();
+        let summary: Vec<_> = samples
+            .iter()
+            .map(|s| (s.time, s.queues[0], s.throughput, s.collisions, s.busy))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (10.0, 10, 100.0, 0, 0.5),
+                (20.0, 20, 50.0, 1, 1.0),
+                (25.0, 25, 60.0, 1, 0.4),
+            ]
+        );
+    }
+
+    // A sampled run is sampled at the end of every interval, warm-up period included.
+    #[test]
+    fn sampled_run() {
+        let lan = |sample| {
+            engine::run(&Params {
+                rate: 200,
+                psize: PacketSize::Constant(1000),
+                duration: 1,
+                qsize: Some(4),
+                warmup: 0.2,
+                sample,
+                resolution: 1e5,
+                seed: 9,
+                ..Params::default()
+            })
+        };
+        let report = lan(Some(0.1));
+        assert_eq!(report.series.len(), 10);
+        assert_eq!(report.series[9].time, 1.0);
+        assert!(report.series.iter().all(|s| s.queues.len() == 10));
+        assert!(report.series.iter().any(|s| s.queues.iter().any(|&q| q > 0)));
+
+        // Samples span the warm-up period, the statistics only what follows.
+        let (warmup, after) = report.series.split_at(2);
+        let collisions: u32 = after.iter().map(|s| s.collisions).sum();
+        assert_eq!(collisions, report.collisions());
+        assert!(warmup.iter().any(|s| s.collisions > 0));
+        let busy: f64 = after.iter().map(|s| s.busy).sum::<f64>() / after.len() as f64;
+        assert!((busy - (1.0 - report.channel.idle_fraction())).abs() < 1e-9);
+        // Over the one second run, throughput is the number of bits processed.
+        assert!(report.series[9].throughput >= report.bits_processed() as f64);
+        assert!(lan(None).series.is_empty());
+    }
+}
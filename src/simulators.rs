@@ -1,37 +1,275 @@
 use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
 use generators::Generator;
-use rand::{thread_rng, Rng};
+use rand::{Rng, StdRng};
 use bit_vec::BitVec;
 use cbuffer::CircularBuffer;
+use rng::{self, Stream};
+use backoff::{Backoff, BackoffPolicy, TRUNCATION};
+use log::{self, EventLog};
+use mac::{MacProtocol, Station};
+use sizes::SizeDistribution;
+use stats::OnlineStats;
+use serde::{Serialize, Serializer};
 
-// Packet holds the value of the time unit that it was generated at and its length.
+// The CSMA/CD timing parameters in bit-times: the carrier sensing period (the interframe gap), the
+// backoff slot and the jam signal sent on detecting a collision. MAX_ATTEMPTS is the default
+// number of failed attempts at transmitting a packet after which it is dropped, 16 as per
+// IEEE 802.3.
+pub const SENSING_BITS: f64 = 96.0;
+pub const BACKOFF_SLOT_BITS: f64 = 512.0;
+pub const JAM_BITS: f64 = 32.0;
+pub const MAX_ATTEMPTS: u32 = 16;
+
+// Timing holds the resolution of the simulation (ticks/s) and the LAN speed (bits/s), along with
+// the CSMA/CD timing parameters converted from bit-times into ticks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Timing {
+    pub resolution: f64,
+    pub lspeed: f64,
+    pub sensing: u32,
+    pub slot: u32,
+    pub jam: u32,
+}
+
+impl Timing {
+    // Timing::new converts the sensing period, backoff slot and jam signal, expressed in bit-times
+    // at the given LAN speed, into ticks.
+    pub fn new(resolution: f64, lspeed: f64) -> Timing {
+        let mut timing = Timing {
+            resolution,
+            lspeed,
+            sensing: 0,
+            slot: 0,
+            jam: 0,
+        };
+        timing.sensing = timing.ticks(SENSING_BITS / lspeed);
+        timing.slot = timing.ticks(BACKOFF_SLOT_BITS / lspeed);
+        timing.jam = timing.ticks(JAM_BITS / lspeed);
+        timing
+    }
+
+    // Timing.ticks converts a duration in seconds into ticks. We round up, a period shorter than
+    // a tick still takes up a tick, but are careful not to do so for durations that are a whole
+    // number of ticks up to floating point error.
+    pub fn ticks(&self, seconds: f64) -> u32 {
+        let ticks = seconds * self.resolution;
+        let rounded = ticks.round();
+        if (ticks - rounded).abs() < 1e-6 {
+            (rounded as u32).max(1)
+        } else {
+            (ticks.ceil() as u32).max(1)
+        }
+    }
+
+    // Timing.seconds converts a number of ticks into seconds.
+    pub fn seconds(&self, ticks: u32) -> f64 {
+        f64::from(ticks) / self.resolution
+    }
+
+    // Timing.bits_per_tick returns the number of bits pushed onto the medium every tick.
+    pub fn bits_per_tick(&self) -> f64 {
+        self.lspeed / self.resolution
+    }
+}
+
+// CsmaMode is what a Server does on sensing the medium. CsmaMode::NonPersistent backs off on
+// finding it busy, as it does after a collision, before sensing it again. CsmaMode::OnePersistent
+// keeps sensing until the medium is idle and transmits right away. CsmaMode::PPersistent keeps
+// sensing until the medium is idle too, then transmits with probability p, deferring for a
+// backoff slot and sensing again otherwise.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CsmaMode {
+    NonPersistent,
+    OnePersistent,
+    PPersistent(f64),
+}
+
+impl FromStr for CsmaMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "unknown CSMA mode '{}', expected 'nonpersistent', '1' or 'p=P' with P in (0, 1]",
+                s
+            )
+        };
+        match s {
+            "nonpersistent" => Ok(CsmaMode::NonPersistent),
+            "1" => Ok(CsmaMode::OnePersistent),
+            _ if s.starts_with("p=") => {
+                let p = s[2..].parse::<f64>().map_err(|_| invalid())?;
+                if p > 0.0 && p <= 1.0 {
+                    Ok(CsmaMode::PPersistent(p))
+                } else {
+                    Err(invalid())
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for CsmaMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CsmaMode::NonPersistent => write!(f, "nonpersistent"),
+            CsmaMode::OnePersistent => write!(f, "1"),
+            CsmaMode::PPersistent(p) => write!(f, "p={}", p),
+        }
+    }
+}
+
+// CSMA modes serialize as they're specified on the command line.
+impl Serialize for CsmaMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// Destination is where a packet is headed, either a single node or every node but its source.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Destination {
+    Unicast(usize),
+    Broadcast,
+}
+
+impl Destination {
+    // Destination.reaches returns whether a packet from source is addressed to the given node.
+    pub fn reaches(&self, source: usize, node: usize) -> bool {
+        match *self {
+            Destination::Unicast(destination) => destination == node,
+            Destination::Broadcast => source != node,
+        }
+    }
+}
+
+// Destinations is how nodes address their packets. Destinations::Uniform sends every packet to a
+// node drawn uniformly at random from the others and Destinations::Broadcast to all of them.
+// Destinations::Node sends every packet to the given node (a file server, say), those of the node
+// itself going to a node drawn uniformly at random.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Destinations {
+    Uniform,
+    Broadcast,
+    Node(usize),
+}
+
+impl Destinations {
+    // Destinations.next returns the destination of the next packet of source, one of the given
+    // number of nodes. A lone node broadcasts its packets, there being no one to receive them.
+    pub fn next(&self, rng: &mut StdRng, source: usize, nodes: usize) -> Destination {
+        match *self {
+            Destinations::Node(node) if node != source => Destination::Unicast(node),
+            Destinations::Uniform | Destinations::Node(_) if nodes > 1 => {
+                let node = rng.gen_range(0, nodes - 1);
+                Destination::Unicast(if node < source { node } else { node + 1 })
+            }
+            _ => Destination::Broadcast,
+        }
+    }
+}
+
+impl FromStr for Destinations {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Destinations::Uniform),
+            "broadcast" => Ok(Destinations::Broadcast),
+            _ => s.parse().map(Destinations::Node).map_err(|_| {
+                format!(
+                    "unknown destinations '{}', expected 'uniform', 'broadcast' or a node",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Destinations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Destinations::Uniform => write!(f, "uniform"),
+            Destinations::Broadcast => write!(f, "broadcast"),
+            Destinations::Node(node) => write!(f, "{}", node),
+        }
+    }
+}
+
+// Destinations serialize as they're specified on the command line.
+impl Serialize for Destinations {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// Packet holds the value of the time unit that it was generated at and its length, along with the
+// node it's sent from and where it's headed. Its id is unique across the LAN, the source in its
+// upper 32 bits and the packet's sequence number amongst those of its source in the lower ones.
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Packet {
+    pub id: u64,
+    pub source: usize,
+    pub destination: Destination,
     pub time_generated: u32,
     pub length: u32,
 }
 
 // Client generates packets according as per the parametrized generators::Generator. We maintain a
 // ticker count to the next time a packet is to be generated, moving forward at ticks of the
-// specified resolution.
+// specified resolution. The Client owns the RNG its generator, packet size distribution and
+// destinations draw from, a packet's length and destination being drawn as it's generated.
 pub struct Client<G: Generator> {
     resolution: f64,
     ticker: u32,
-    packet_length: u32,
+    generated: u32,
+    sizes: Box<dyn SizeDistribution>,
+    source: usize,
+    nodes: usize,
+    destinations: Destinations,
     generator: G,
+    rng: StdRng,
 }
 
 impl<G: Generator> Client<G> {
     // Client::new seeds the ticker using the provided generator.
-    pub fn new(generator: G, resolution: f64, packet_length: u32) -> Self {
+    pub fn new(
+        mut generator: G,
+        mut rng: StdRng,
+        resolution: f64,
+        sizes: Box<dyn SizeDistribution>,
+    ) -> Self {
         Client {
-            resolution: resolution,
-            ticker: generator.next_event(resolution),
-            packet_length: packet_length,
-            generator: generator,
+            resolution,
+            ticker: generator.next_event(&mut rng, resolution),
+            generated: 0,
+            sizes,
+            source: 0,
+            nodes: 1,
+            destinations: Destinations::Broadcast,
+            generator,
+            rng,
         }
     }
 
+    // Client.with_destinations has the Client, that of the given node out of the given number of
+    // them, address its packets to the given destinations. A Client broadcasts its packets, as
+    // node 0, by default.
+    pub fn with_destinations(
+        mut self,
+        source: usize,
+        nodes: usize,
+        destinations: Destinations,
+    ) -> Self {
+        self.source = source;
+        self.nodes = nodes;
+        self.destinations = destinations;
+        self
+    }
+
     // The caller is responsible for calling Client.tick() at fixed time intervals, moving the
     // Client simulator one time unit per call. We return a Option<Packet> indicating whether or
     // not a packet is generated in the most recently completed time unit.
@@ -41,41 +279,245 @@ impl<G: Generator> Client<G> {
     pub fn tick(&mut self, current_time: u32) -> Option<Packet> {
         // TODO(irfansharif): Resolution mismatch; no possibility of generating multiple packets.
         if self.ticker == 0 {
-            self.ticker = self.generator.next_event(self.resolution);
-            return Some(Packet {
-                time_generated: current_time,
-                length: self.packet_length,
-            });
+            return Some(self.generate(current_time));
         }
 
         self.ticker -= 1;
         if self.ticker == 0 {
-            self.ticker = self.generator.next_event(self.resolution);
-            Some(Packet {
-                time_generated: current_time,
-                length: self.packet_length,
-            })
+            Some(self.generate(current_time))
         } else {
             None
         }
     }
+
+    // Client.generate generates a packet at the current time, its length dictated by the
+    // generator if it so wishes, and draws the time to the next one.
+    fn generate(&mut self, current_time: u32) -> Packet {
+        let length = match self.generator.packet_length() {
+            Some(length) => length,
+            None => self.sizes.next_size(&mut self.rng),
+        };
+        let destination = self.destinations.next(&mut self.rng, self.source, self.nodes);
+        self.ticker = self.generator.next_event(&mut self.rng, self.resolution);
+        self.generated += 1;
+        Packet {
+            id: (self.source as u64) << 32 | u64::from(self.generated - 1),
+            source: self.source,
+            destination,
+            time_generated: current_time,
+            length,
+        }
+    }
+
+    // Client.skip fast-forwards the Client to the tick at which the next packet is generated,
+    // returning the number of ticks skipped. The caller is expected to call Client.tick() for that
+    // tick next, which is then guaranteed to return a packet. This lets the event scheduler follow
+    // the exact same arrival process without ticking through the idle period.
+    pub fn skip(&mut self) -> u32 {
+        let skipped = self.ticker.saturating_sub(1);
+        self.ticker -= skipped;
+        skipped
+    }
+}
+
+// Queue is a FIFO queue of packets awaiting transmission. The queue is optionally bounded, packets
+// arriving to a full queue are dropped (tail-drop).
+pub struct Queue {
+    packets: VecDeque<Packet>,
+    limit: Option<usize>,
+}
+
+impl Queue {
+    // Queue::new returns an empty Queue holding at most limit packets, limit == None for an
+    // infinite queue.
+    pub fn new(limit: Option<usize>) -> Queue {
+        Queue {
+            packets: VecDeque::new(),
+            limit,
+        }
+    }
+
+    // Queue.push enqueues the packet, returning false if the queue was full and it was dropped.
+    pub fn push(&mut self, packet: Packet) -> bool {
+        match self.limit {
+            Some(limit) if self.packets.len() >= limit => false,
+            _ => {
+                self.packets.push_back(packet);
+                true
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Packet> {
+        self.packets.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
 }
 
 // ServerStatistics is the set of statistics we care about post-simulation as far as the Server is
-// concerned.
+// concerned. packets_dropped counts packets dropped after exhausting their retries,
+// packets_tail_dropped those dropped on arrival to a full queue. collisions counts the
+// transmissions aborted on detecting another signal on the medium. sojourn holds the sojourn time,
+// in seconds, of every packet processed and retries[k] the number of those processed after
+// exactly k retries. On the receiving end, packets_received and bits_received count the packets
+// addressed to the Server (broadcasts included) it received, delay holding their end-to-end delay
+// in seconds, from their generation at the source to their last bit reaching the Server.
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct ServerStatistics {
     pub packets_processed: u32,
     pub packets_generated: u32,
     pub packets_dropped: u32,
+    pub packets_tail_dropped: u32,
+    pub bits_processed: u64,
+    pub collisions: u32,
+    #[serde(skip)]
+    pub sojourn: OnlineStats,
+    pub retries: Vec<u32>,
+    pub packets_received: u32,
+    pub bits_received: u64,
+    #[serde(skip)]
+    pub delay: OnlineStats,
 }
 
 impl ServerStatistics {
-    fn new() -> ServerStatistics {
+    pub fn new() -> ServerStatistics {
         ServerStatistics {
             packets_processed: 0,
             packets_generated: 0,
             packets_dropped: 0,
+            packets_tail_dropped: 0,
+            bits_processed: 0,
+            collisions: 0,
+            sojourn: OnlineStats::new(),
+            retries: vec![],
+            packets_received: 0,
+            bits_received: 0,
+            delay: OnlineStats::new(),
+        }
+    }
+
+    // ServerStatistics.processed records a packet of the given length processed after spending
+    // sojourn seconds in the system and being retried the given number of times.
+    pub fn processed(&mut self, length: u32, sojourn: f64, retries: u32) {
+        self.packets_processed += 1;
+        self.bits_processed += u64::from(length);
+        self.sojourn.add(sojourn);
+        let retries = retries as usize;
+        if self.retries.len() <= retries {
+            self.retries.resize(retries + 1, 0);
+        }
+        self.retries[retries] += 1;
+    }
+
+    // ServerStatistics.received records a packet of the given length received delay seconds after
+    // it was generated.
+    pub fn received(&mut self, length: u32, delay: f64) {
+        self.packets_received += 1;
+        self.bits_received += u64::from(length);
+        self.delay.add(delay);
+    }
+
+    // ServerStatistics.mean_retries returns the average number of retries per packet processed,
+    // none if no packet was.
+    pub fn mean_retries(&self) -> f64 {
+        if self.packets_processed == 0 {
+            return 0.0;
         }
+        let retries: u64 = self.retries
+            .iter()
+            .enumerate()
+            .map(|(k, &n)| k as u64 * u64::from(n))
+            .sum();
+        retries as f64 / f64::from(self.packets_processed)
+    }
+}
+
+// ChannelStatistics counts the ticks during which the medium carried no transmitter (idle),
+// exactly one transmitter (busy) or more than one (collision). Transmitters are counted where
+// they write to the medium, disregarding propagation. Jamming nodes count as transmitters,
+// jam_ticks is the number of ticks during which at least one was jamming and wasted_ticks the
+// number of ticks lost to either collisions or jamming.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChannelStatistics {
+    pub idle_ticks: u64,
+    pub busy_ticks: u64,
+    pub collision_ticks: u64,
+    pub jam_ticks: u64,
+    pub wasted_ticks: u64,
+}
+
+impl ChannelStatistics {
+    pub fn new() -> ChannelStatistics {
+        ChannelStatistics {
+            idle_ticks: 0,
+            busy_ticks: 0,
+            collision_ticks: 0,
+            jam_ticks: 0,
+            wasted_ticks: 0,
+        }
+    }
+
+    // ChannelStatistics.record records the given number of ticks during which the given number
+    // of nodes were transmitting, of which the given number were jamming.
+    pub fn record(&mut self, transmitters: usize, jammers: usize, ticks: u64) {
+        match transmitters {
+            0 => self.idle_ticks += ticks,
+            1 => self.busy_ticks += ticks,
+            _ => self.collision_ticks += ticks,
+        }
+        if jammers > 0 {
+            self.jam_ticks += ticks;
+        }
+        if transmitters > 1 || jammers > 0 {
+            self.wasted_ticks += ticks;
+        }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.idle_ticks + self.busy_ticks + self.collision_ticks
+    }
+
+    // ChannelStatistics.utilization returns the fraction of ticks the medium carried exactly one
+    // transmitter.
+    pub fn utilization(&self) -> f64 {
+        self.fraction(self.busy_ticks)
+    }
+
+    // ChannelStatistics.collision_fraction returns the fraction of ticks the medium carried more
+    // than one transmitter.
+    pub fn collision_fraction(&self) -> f64 {
+        self.fraction(self.collision_ticks)
+    }
+
+    // ChannelStatistics.idle_fraction returns the fraction of ticks the medium was idle.
+    pub fn idle_fraction(&self) -> f64 {
+        self.fraction(self.idle_ticks)
+    }
+
+    // ChannelStatistics.jam_fraction returns the fraction of ticks the medium carried a jam
+    // signal.
+    pub fn jam_fraction(&self) -> f64 {
+        self.fraction(self.jam_ticks)
+    }
+
+    // ChannelStatistics.wasted_fraction returns the fraction of ticks lost to collisions or
+    // jamming.
+    pub fn wasted_fraction(&self) -> f64 {
+        self.fraction(self.wasted_ticks)
+    }
+
+    fn fraction(&self, ticks: u64) -> f64 {
+        if self.ticks() == 0 {
+            return 0.0;
+        }
+        ticks as f64 / self.ticks() as f64
     }
 }
 
@@ -86,84 +528,108 @@ enum ServerState {
         counter: u32,
         busy: bool,
         current_packet: Packet,
+        retries: u32,
     },
     Transmitting {
         bits_processed: f64,
         current_packet: Packet,
+        retries: u32,
+    },
+    Jamming {
+        counter: u32,
+        current_packet: Packet,
+        retries: u32,
     },
     Waiting {
         counter: u32,
         wait_time: u32,
         current_packet: Packet,
+        retries: u32,
     },
 }
 
-// Server stores packets in a queue and processes them.
-pub struct Server<G: Generator> {
-    id: usize,
-    client: Client<G>,
-    queue: VecDeque<Packet>,
-    resolution: f64,
-    statistics: ServerStatistics,
+// Csma is the CSMA/CD MacProtocol, sensing the medium as per its CsmaMode before transmitting and
+// backing off on detecting a collision. The number of retries of the packet being processed is
+// kept alongside it in the ServerState, starting afresh with every packet.
+pub struct Csma {
     state: ServerState,
-    persistence: bool,
-    // Processing variables
-    pspeed: f64,
-    retries: u32,
+    timing: Timing,
+    csma: CsmaMode,
+    backoff: Box<dyn Backoff>,
+    attempts: u32,
+    rng: StdRng,
 }
 
-impl<G: Generator> Server<G> {
-    // Server::new returns a Server.
-    pub fn new(
-        id: usize,
-        generator: G,
-        packet_length: u32,
-        resolution: f64,
-        pspeed: f64,
-        persistence: bool,
-    ) -> Self {
-        Server {
-            id: id,
-            client: Client::new(generator, resolution, packet_length),
-            queue: VecDeque::new(),
-            resolution: resolution,
-            statistics: ServerStatistics::new(),
+impl Csma {
+    // Csma::new returns an idle Csma for the node with the given id, its backoff drawing from the
+    // node's MAC stream of the master seed (see rng::stream).
+    pub fn new(id: usize, timing: Timing, csma: CsmaMode, seed: u64) -> Csma {
+        Csma {
             state: ServerState::Idle,
-            pspeed: pspeed,
-            retries: 0,
-            persistence: persistence,
+            timing,
+            csma,
+            backoff: BackoffPolicy::Exponential.build(TRUNCATION),
+            attempts: MAX_ATTEMPTS,
+            rng: rng::stream(seed, id, Stream::Mac),
         }
     }
 
-    // Server.enqueue enqueues a packet for delivery. If the packet is to be dropped (due to the
-    // internal queue being full it is recorded in the Server's internal statistics.
-    pub fn enqueue(&mut self, packet: Packet) {
-        // Infinite queue, limit == None.
-        self.queue.push_back(packet);
+    // Csma.with_backoff sets the backoff policy, truncated binary exponential backoff by default.
+    pub fn with_backoff(mut self, backoff: Box<dyn Backoff>) -> Self {
+        self.backoff = backoff;
+        self
     }
 
-    // Server.tick checks to see if a packet is currently being processed, and if so,
-    // increments Server.bits_processed, and if the resulting sum is equal to the bits
-    // in the packet, then it returns the packet and resets the state of Server.
-    pub fn tick(
-        &mut self,
-        local_state: &mut BitVec,
-        medium: &Medium,
-        current_time: u32,
-    ) -> Option<Packet> {
-        if let Some(packet) = self.client.tick(current_time) {
-            self.statistics.packets_generated += 1;
-            self.enqueue(packet);
+    // Csma.with_attempts sets the number of failed attempts at transmitting a packet after which
+    // it's dropped, MAX_ATTEMPTS by default.
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    // Csma.backoff_or_drop backs off before sensing the medium again, having failed to transmit
+    // the current packet the given number of times, or drops it altogether once out of attempts.
+    fn backoff_or_drop(&mut self, station: &mut Station, current_packet: Packet, retries: u32) {
+        if retries >= self.attempts {
+            station.log(log::Kind::Dropped, &current_packet, retries);
+            self.state = ServerState::Idle;
+            station.statistics.packets_dropped += 1;
+        } else {
+            let wait_time =
+                self.backoff.slots(&mut self.rng, retries).saturating_mul(self.timing.slot);
+            station.log(log::Kind::Backoff, &current_packet, retries);
+            self.state = ServerState::Waiting {
+                counter: 0,
+                wait_time,
+                current_packet,
+                retries,
+            };
+        }
+    }
+
+    // Csma.defers returns whether, having sensed the medium idle, the node defers its
+    // transmission by a backoff slot. Only p-persistent nodes do, with probability 1 - p.
+    fn defers(&mut self) -> bool {
+        match self.csma {
+            CsmaMode::PPersistent(p) => self.rng.gen::<f64>() >= p,
+            _ => false,
         }
+    }
+}
+
+impl MacProtocol for Csma {
+    fn tick(&mut self, station: &mut Station) -> Option<(Packet, u32)> {
         loop {
             match self.state {
                 ServerState::Idle => {
-                    match self.queue.pop_front() {
+                    match station.queue.pop() {
                         Some(packet) => {
+                            station.log(log::Kind::Sensing, &packet, 0);
                             self.state = ServerState::Sensing {
                                 counter: 0,
                                 busy: false,
                                 current_packet: packet,
+                                retries: 0,
                             }
                         }
                         None => {
@@ -176,95 +642,120 @@ impl<G: Generator> Server<G> {
                     counter,
                     busy,
                     current_packet,
+                    retries,
                 } => {
-                    // TODO(irfansharif): Factor in resolution.
-                    if counter < 96 {
+                    if counter < self.timing.sensing {
                         self.state = ServerState::Sensing {
                             counter: counter + 1,
-                            busy: medium.is_busy(self.id) || busy,
-                            current_packet: current_packet,
+                            busy: station.busy() || busy,
+                            current_packet,
+                            retries,
                         };
                         break;
+                    } else if busy && self.csma != CsmaMode::NonPersistent {
+                        assert!(counter == self.timing.sensing);
+
+                        // Persistent modes keep sensing until the medium is idle.
+                        station.log(log::Kind::Sensing, &current_packet, retries);
+                        self.state = ServerState::Sensing {
+                            counter: 0,
+                            busy: false,
+                            current_packet,
+                            retries,
+                        };
                     } else if busy {
-                        assert!(counter == 96);
+                        assert!(counter == self.timing.sensing);
 
-                        self.retries += 1;
-                        if self.retries > 10 {
-                            self.state = ServerState::Idle;
-                            self.statistics.packets_dropped += 1;
-                        } else {
-                            // TODO(irfansharif): Factor in resolution.
-                            let mut wait_time: u32 =
-                                thread_rng().gen_range(0, 2u32.pow(self.retries) - 1) * 512;
-                            if self.persistence {
-                                // Persistent mode, wait_time == 0.
-                                wait_time = 0;
-                            }
-                            self.state = ServerState::Waiting {
-                                counter: 0,
-                                wait_time: wait_time,
-                                current_packet,
-                            };
-                        }
+                        self.backoff_or_drop(station, current_packet, retries + 1);
+                    } else if self.defers() {
+                        assert!(counter == self.timing.sensing);
+
+                        station.log(log::Kind::Deferred, &current_packet, retries);
+                        self.state = ServerState::Waiting {
+                            counter: 0,
+                            wait_time: self.timing.slot,
+                            current_packet,
+                            retries,
+                        };
                     } else {
-                        assert!(counter == 96);
+                        assert!(counter == self.timing.sensing);
 
+                        station.log(log::Kind::Transmitting, &current_packet, retries);
                         self.state = ServerState::Transmitting {
                             bits_processed: 0.0,
                             current_packet,
+                            retries,
                         };
                     }
                 }
                 ServerState::Transmitting {
                     bits_processed,
                     current_packet,
+                    retries,
                 } => {
-                    if !medium.is_busy(self.id) {
-                        let bits_processed = bits_processed + (self.pspeed / self.resolution);
-                        local_state.set(self.id, true);
+                    if !station.busy() {
+                        let bits_processed = bits_processed + self.timing.bits_per_tick();
+                        station.transmit();
                         if (bits_processed as u32) >= current_packet.length {
-                            self.statistics.packets_processed += 1;
                             self.state = ServerState::Idle;
-                            return Some(current_packet);
+                            return Some((current_packet, retries));
                         }
                         self.state = ServerState::Transmitting {
                             bits_processed,
                             current_packet,
+                            retries,
                         };
                         break;
                     } else {
-                        self.retries += 1;
-                        if self.retries > 10 {
-                            self.state = ServerState::Idle;
-                            self.statistics.packets_dropped += 1;
-                        } else {
-                            let wait_time: u32 =
-                                thread_rng().gen_range(0, 2u32.pow(self.retries) - 1) * 512;
-                            self.state = ServerState::Waiting {
-                                counter: 0,
-                                wait_time: wait_time,
-                                current_packet,
-                            };
-                        }
+                        // Abort the transmission, jamming the medium in its stead so that every
+                        // other node involved detects the collision.
+                        station.statistics.collisions += 1;
+                        station.log(log::Kind::Collision, &current_packet, retries);
+                        self.state = ServerState::Jamming {
+                            counter: 0,
+                            current_packet,
+                            retries,
+                        };
                     }
                 }
+                ServerState::Jamming {
+                    counter,
+                    current_packet,
+                    retries,
+                } => {
+                    if counter < self.timing.jam {
+                        station.transmit();
+                        self.state = ServerState::Jamming {
+                            counter: counter + 1,
+                            current_packet,
+                            retries,
+                        };
+                        break;
+                    }
+
+                    self.backoff_or_drop(station, current_packet, retries + 1);
+                }
                 ServerState::Waiting {
                     counter,
                     wait_time,
                     current_packet,
+                    retries,
                 } => {
                     if counter < wait_time {
                         self.state = ServerState::Waiting {
                             counter: counter + 1,
-                            wait_time: wait_time,
-                            current_packet: current_packet,
+                            wait_time,
+                            current_packet,
+                            retries,
                         };
                         break;
                     } else {
+                        station.log(log::Kind::Sensing, &current_packet, retries);
                         self.state = ServerState::Sensing {
                             counter: 0,
                             busy: false,
                             current_packet,
+                            retries,
                         };
                     }
                 }
@@ -273,6 +764,156 @@ impl<G: Generator> Server<G> {
         None
     }
 
+    fn is_jamming(&self) -> bool {
+        matches!(self.state, ServerState::Jamming { .. })
+    }
+}
+
+// Server stores packets in a queue and processes them, as its MacProtocol (CSMA/CD by default)
+// sees fit. Given an EventLog, the Server logs every stage of its packets' lifecycle.
+pub struct Server<G: Generator, M: MacProtocol = Csma> {
+    id: usize,
+    client: Client<G>,
+    queue: Queue,
+    timing: Timing,
+    statistics: ServerStatistics,
+    mac: M,
+    log: Option<EventLog>,
+}
+
+impl<G: Generator> Server<G> {
+    // Server::new returns a CSMA/CD Server whose Client draws the length of its packets from
+    // sizes. The Server's Client and its backoff draw from independent streams derived from the
+    // master seed and the Server's id (see rng::stream).
+    pub fn new(
+        id: usize,
+        generator: G,
+        sizes: Box<dyn SizeDistribution>,
+        timing: Timing,
+        csma: CsmaMode,
+        qsize: Option<usize>,
+        seed: u64,
+    ) -> Self {
+        Server {
+            id,
+            client: Client::new(
+                generator,
+                rng::stream(seed, id, Stream::Traffic),
+                timing.resolution,
+                sizes,
+            ).with_destinations(id, 1, Destinations::Broadcast),
+            queue: Queue::new(qsize),
+            timing,
+            statistics: ServerStatistics::new(),
+            mac: Csma::new(id, timing, csma, seed),
+            log: None,
+        }
+    }
+
+    // Server.with_backoff sets the Server's backoff policy, truncated binary exponential backoff
+    // by default.
+    pub fn with_backoff(mut self, backoff: Box<dyn Backoff>) -> Self {
+        self.mac = self.mac.with_backoff(backoff);
+        self
+    }
+
+    // Server.with_attempts sets the number of failed attempts at transmitting a packet after
+    // which the Server drops it, MAX_ATTEMPTS by default.
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.mac = self.mac.with_attempts(attempts);
+        self
+    }
+}
+
+impl<G: Generator, M: MacProtocol> Server<G, M> {
+    // Server.with_mac has the Server process its packets as per the given MacProtocol instead.
+    pub fn with_mac<N: MacProtocol>(self, mac: N) -> Server<G, N> {
+        Server {
+            id: self.id,
+            client: self.client,
+            queue: self.queue,
+            timing: self.timing,
+            statistics: self.statistics,
+            mac,
+            log: self.log,
+        }
+    }
+
+    // Server.with_destinations has the Server, one of the given number of nodes, address its
+    // packets to the given destinations, broadcasting them by default.
+    pub fn with_destinations(mut self, nodes: usize, destinations: Destinations) -> Self {
+        self.client = self.client.with_destinations(self.id, nodes, destinations);
+        self
+    }
+
+    // Server.with_log has the Server log its events to the given EventLog (see Server.take_log).
+    pub fn with_log(mut self, log: EventLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    // Server.enqueue enqueues a packet for delivery. If the packet is to be dropped (due to the
+    // internal queue being full) it is recorded in the Server's internal statistics. It returns
+    // whether the packet was enqueued.
+    pub fn enqueue(&mut self, packet: Packet) -> bool {
+        if !self.queue.push(packet) {
+            self.statistics.packets_tail_dropped += 1;
+            return false;
+        }
+        true
+    }
+
+    // Server.tick checks to see if a packet is currently being processed, and if so,
+    // increments Server.bits_processed, and if the resulting sum is equal to the bits
+    // in the packet, then it returns the packet and resets the state of Server. The caller is
+    // expected to hand the packet over to the Medium (see Medium.transmitted) for the Servers it's
+    // addressed to to receive it.
+    pub fn tick(
+        &mut self,
+        local_state: &mut BitVec,
+        medium: &Medium,
+        current_time: u32,
+    ) -> Option<Packet> {
+        for packet in medium.received(self.id, current_time) {
+            let delay = self.timing.seconds(current_time - packet.time_generated);
+            self.statistics.received(packet.length, delay);
+            if let Some(ref mut log) = self.log {
+                log.received(current_time, self.id, &packet);
+            }
+        }
+        if let Some(packet) = self.client.tick(current_time) {
+            self.statistics.packets_generated += 1;
+            self.log(current_time, log::Kind::Generated, &packet, 0);
+            if !self.enqueue(packet) {
+                self.log(current_time, log::Kind::TailDropped, &packet, 0);
+            }
+        }
+
+        let (packet, retries) = self.mac.tick(&mut Station {
+            id: self.id,
+            now: current_time,
+            queue: &mut self.queue,
+            statistics: &mut self.statistics,
+            log: &mut self.log,
+            medium,
+            local_state,
+        })?;
+        self.statistics.processed(
+            packet.length,
+            self.timing.seconds(current_time - packet.time_generated),
+            retries,
+        );
+        self.log(current_time, log::Kind::Transmitted, &packet, retries);
+        Some(packet)
+    }
+
+    // Server.log logs an event concerning the given packet, if the Server has an EventLog.
+    fn log(&mut self, current_time: u32, kind: log::Kind, packet: &Packet, retries: u32) {
+        if let Some(ref mut log) = self.log {
+            log.node(current_time, self.id, kind, packet, retries);
+        }
+    }
+
     // Server.packets_processed returns the number of packets processed by the Server thus far.
     pub fn packets_processed(&self) -> u32 {
         self.statistics.packets_processed
@@ -287,54 +928,193 @@ impl<G: Generator> Server<G> {
     pub fn packets_dropped(&self) -> u32 {
         self.statistics.packets_dropped
     }
+
+    // Server.queue_len returns the number of packets queued at the Server, awaiting their turn
+    // behind the one being processed.
+    pub fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    // Server.is_jamming returns whether the Server jammed the medium on its last tick.
+    pub fn is_jamming(&self) -> bool {
+        self.mac.is_jamming()
+    }
+
+    // Server.statistics returns the Server's statistics thus far.
+    pub fn statistics(&self) -> &ServerStatistics {
+        &self.statistics
+    }
+
+    // Server.reset_statistics discards the Server's statistics thus far, the packets in flight
+    // are unaffected.
+    pub fn reset_statistics(&mut self) {
+        self.statistics = ServerStatistics::new();
+    }
+
+    // Server.take_log returns the Server's EventLog, if any, with every event logged thus far.
+    pub fn take_log(&mut self) -> Option<EventLog> {
+        self.log.take()
+    }
 }
 
 // Medium contains a circular buffer, with a bit vector of size n at each index
 //
-// The bit vectors represent the n possible writes that n nodes can perform at one time
+// The bit vectors represent the n possible writes that n nodes can perform at one time. The signal
+// written by node j reaches node i delays[i][j] ticks later, the buffer holds as many ticks as the
+// longest such delay. The Medium carries the packets transmitted over it too, the Servers they're
+// addressed to receiving them as their last bit reaches them. Given an EventLog, the Medium logs
+// every change in the state of the channel, keeping count of the ticks written to it.
 pub struct Medium {
     tracks: CircularBuffer<BitVec>,
+    delays: Vec<Vec<usize>>,
     num_nodes: usize,
+    frames: VecDeque<(u32, Packet)>,
+    horizon: u32,
+    statistics: ChannelStatistics,
+    log: Option<EventLog>,
+    ticks: u32,
+    channel: log::Kind,
 }
 
 impl Medium {
+    // Medium::new returns a Medium where every node sees every other node's signal bsize ticks
+    // after it was written.
     pub fn new(num_nodes: usize, bsize: usize) -> Medium {
+        Medium::with_delays(vec![vec![bsize as u32; num_nodes]; num_nodes])
+    }
+
+    // Medium::with_delays returns a Medium where the signal of node j reaches node i delays[i][j]
+    // ticks after it was written (see topology::Topology.delays).
+    pub fn with_delays(delays: Vec<Vec<u32>>) -> Medium {
+        let num_nodes = delays.len();
+        let delays: Vec<Vec<usize>> = delays
+            .into_iter()
+            .map(|row| row.into_iter().map(|d| d as usize).collect())
+            .collect();
+        let bsize = delays.iter().flat_map(|row| row.iter()).cloned().max();
         Medium {
-            tracks: CircularBuffer::new(bsize, BitVec::from_elem(num_nodes, false)),
-            num_nodes: num_nodes,
+            tracks: CircularBuffer::new(
+                bsize.unwrap_or(1),
+                BitVec::from_elem(num_nodes, false),
+            ),
+            delays,
+            num_nodes,
+            frames: VecDeque::new(),
+            horizon: bsize.unwrap_or(0) as u32,
+            statistics: ChannelStatistics::new(),
+            log: None,
+            ticks: 0,
+            channel: log::Kind::ChannelIdle,
         }
     }
 
+    // Medium.with_log has the Medium log the state of the channel to the given EventLog (see
+    // Medium.take_log).
+    pub fn with_log(mut self, log: EventLog) -> Medium {
+        self.log = Some(log);
+        self
+    }
+
     pub fn tick(&mut self) {
         self.tracks.tick();
     }
 
-    fn is_busy(&self, id: usize) -> bool {
+    // Medium.is_busy returns whether another node's signal reaches the given node this tick.
+    pub fn is_busy(&self, id: usize) -> bool {
         assert!(id < self.num_nodes);
-        let mut mask = BitVec::from_elem(self.num_nodes, true);
-        mask.set(id, false);
-        mask.intersect(&self.tracks.read());
-        mask.any()
+        (0..self.num_nodes).any(|j| {
+            j != id && self.tracks.read_behind(self.delays[id][j])[j]
+        })
+    }
+
+    // Medium.transmitted hands over a packet whose last bit was written to the Medium this tick.
+    pub fn transmitted(&mut self, packet: Packet) {
+        self.frames.push_back((self.ticks, packet));
+    }
+
+    // Medium.received returns the packets addressed to the given node whose last bit reaches it
+    // at the given tick, the first tick yet to be written.
+    fn received(&self, id: usize, current_time: u32) -> Vec<Packet> {
+        if self.frames.is_empty() {
+            return vec![];
+        }
+        self.frames
+            .iter()
+            .filter(|&&(tick, ref packet)| {
+                packet.destination.reaches(packet.source, id) &&
+                    tick + self.delays[id][packet.source] as u32 == current_time
+            })
+            .map(|&(_, packet)| packet)
+            .collect()
     }
 
+    // Medium.write writes the state of every node for the current tick, a node's bit being set if
+    // it is transmitting.
     pub fn write(&mut self, state: BitVec) {
+        self.write_with_jam(state, 0);
+    }
+
+    // Medium.write_with_jam is Medium.write where the given number of the transmitting nodes are
+    // jamming.
+    pub fn write_with_jam(&mut self, state: BitVec, jammers: usize) {
         assert!(state.len() == self.tracks.read().len());
+        let transmitters = state.iter().filter(|&b| b).count();
+        self.statistics.record(transmitters, jammers, 1);
         self.tracks.write(state);
+
+        let channel = log::Kind::channel(transmitters, jammers);
+        if channel != self.channel {
+            if let Some(ref mut log) = self.log {
+                log.channel(self.ticks, channel);
+            }
+            self.channel = channel;
+        }
+        self.ticks += 1;
+
+        // Packets are done with once they've reached every node.
+        while self.frames.front().is_some_and(|&(tick, _)| tick + self.horizon < self.ticks) {
+            self.frames.pop_front();
+        }
+    }
+
+    // Medium.statistics returns the channel statistics for every tick written thus far.
+    pub fn statistics(&self) -> ChannelStatistics {
+        self.statistics
+    }
+
+    // Medium.reset_statistics discards the channel statistics for every tick written thus far.
+    pub fn reset_statistics(&mut self) {
+        self.statistics = ChannelStatistics::new();
+    }
+
+    // Medium.take_log returns the Medium's EventLog, if any, with every event logged thus far.
+    pub fn take_log(&mut self) -> Option<EventLog> {
+        self.log.take()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::generators::Deterministic;
+    use super::super::generators::{Deterministic, Markov};
+    use engine::{self, Params};
+    use sizes::{Constant, PacketSize};
 
     #[test]
     fn client_packet_generation() {
-        let mut c = Client::new(Deterministic::new(0.5), 1.0, 1);
+        let mut c = Client::new(
+            Deterministic::new(0.5),
+            rng::stream(0, 0, Stream::Traffic),
+            1.0,
+            Box::new(Constant(1)),
+        );
         assert!(c.tick(0).is_none());
         assert!(
             c.tick(1).unwrap() ==
                 Packet {
+                    id: 0,
+                    source: 0,
+                    destination: Destination::Broadcast,
                     time_generated: 1,
                     length: 1,
                 }
@@ -391,36 +1171,45 @@ mod tests {
         let mut server = Server::new(
             0, // id
             Deterministic::new(0.5), // generator
-            1, // psize
-            1.0, // resolution
-            1.0, // lspeed
-            false, // persistence
+            Box::new(Constant(1)), // psize
+            Timing::new(1.0, 1.0), // timing
+            CsmaMode::NonPersistent, // csma
+            None, // qsize
+            0, // seed
         );
         let mut state = BitVec::from_elem(1, false);
         server.tick(&mut state, &medium, 1);
-        assert!(server.state == ServerState::Idle);
+        assert!(server.mac.state == ServerState::Idle);
         server.tick(&mut state, &medium, 1);
         assert!(
-            server.state ==
+            server.mac.state ==
                 ServerState::Sensing {
                     counter: 1,
                     busy: false,
                     current_packet: Packet {
+                        id: 0,
+                        source: 0,
+                        destination: Destination::Broadcast,
                         time_generated: 1,
                         length: 1,
                     },
+                    retries: 0,
                 }
         );
         server.tick(&mut state, &medium, 2);
         assert!(
-            server.state ==
+            server.mac.state ==
                 ServerState::Sensing {
                     counter: 2,
                     busy: false,
                     current_packet: Packet {
+                        id: 0,
+                        source: 0,
+                        destination: Destination::Broadcast,
                         time_generated: 1,
                         length: 1,
                     },
+                    retries: 0,
                 }
         );
     }
@@ -433,24 +1222,29 @@ mod tests {
         let mut server = Server::new(
             0, // id
             Deterministic::new(0.5), // generator
-            1, // psize
-            1.0, // resolution
-            1.0, // lspeed
-            false, // persistence
+            Box::new(Constant(1)), // psize
+            Timing::new(1.0, 1.0), // timing
+            CsmaMode::NonPersistent, // csma
+            None, // qsize
+            0, // seed
         );
         let mut state = BitVec::from_elem(2, true);
         server.tick(&mut state, &medium, 1);
-        assert!(server.state == ServerState::Idle);
+        assert!(server.mac.state == ServerState::Idle);
         server.tick(&mut state, &medium, 1);
         assert!(
-            server.state ==
+            server.mac.state ==
                 ServerState::Sensing {
                     counter: 1,
                     busy: true,
                     current_packet: Packet {
+                        id: 0,
+                        source: 0,
+                        destination: Destination::Broadcast,
                         time_generated: 1,
                         length: 1,
                     },
+                    retries: 0,
                 }
         );
     }
@@ -461,45 +1255,452 @@ mod tests {
         let mut server = Server::new(
             0, // id
             Deterministic::new(0.5), // generator
-            2, // psize
-            1.0, // resolution
-            1.0, // lspeed
-            false, // persistence
+            Box::new(Constant(2)), // psize
+            Timing::new(1.0, 1.0), // timing
+            CsmaMode::NonPersistent, // csma
+            None, // qsize
+            0, // seed
         );
         let mut state = BitVec::from_elem(2, false);
         server.tick(&mut state, &medium, 1);
-        assert!(server.state == ServerState::Idle);
+        assert!(server.mac.state == ServerState::Idle);
         server.tick(&mut state, &medium, 2);
         assert!(
-            server.state ==
+            server.mac.state ==
                 ServerState::Sensing {
                     counter: 1,
                     busy: false,
                     current_packet: Packet {
+                        id: 0,
+                        source: 0,
+                        destination: Destination::Broadcast,
                         time_generated: 2,
                         length: 2,
                     },
+                    retries: 0,
                 }
         );
         medium.write(BitVec::from_elem(2, false));
-        server.state = ServerState::Sensing {
-            counter: 96,
+        server.mac.state = ServerState::Sensing {
+            counter: server.mac.timing.sensing,
             busy: false,
             current_packet: Packet {
+                id: 0,
+                source: 0,
+                destination: Destination::Broadcast,
                 time_generated: 2,
                 length: 2,
             },
+            retries: 0,
         };
         server.tick(&mut state, &medium, 3);
         assert!(
-            server.state ==
+            server.mac.state ==
                 ServerState::Transmitting {
                     bits_processed: 1.0,
                     current_packet: Packet {
+                        id: 0,
+                        source: 0,
+                        destination: Destination::Broadcast,
                         time_generated: 2,
                         length: 2,
                     },
+                    retries: 0,
                 }
         );
     }
+
+    // run_lan simulates a small, loaded LAN and returns (tick, time_generated) for every packet
+    // processed along with the number of packets dropped.
+    fn run_lan(seed: u64) -> (Vec<(u32, u32)>, u32) {
+        let num_nodes = 4;
+        let timing = Timing::new(1e6, 1e6);
+        let mut servers: Vec<_> = (0..num_nodes)
+            .map(|id| {
+                Server::new(
+                    id, // id
+                    Markov::new(400.0), // generator
+                    Box::new(Constant(200)), // psize
+                    timing, // timing
+                    CsmaMode::NonPersistent, // csma
+                    None, // qsize
+                    seed, // seed
+                )
+            })
+            .collect();
+        let mut medium = Medium::new(num_nodes, 26);
+        let mut processed = vec![];
+        for i in 0..200_000 {
+            let mut local_state = BitVec::from_elem(num_nodes, false);
+            for server in servers.iter_mut() {
+                if let Some(p) = server.tick(&mut local_state, &medium, i) {
+                    processed.push((i, p.time_generated));
+                }
+            }
+            medium.write(local_state);
+            medium.tick();
+        }
+        let dropped = servers.iter().map(|s| s.packets_dropped()).sum();
+        (processed, dropped)
+    }
+
+    #[test]
+    fn seeded_runs_are_reproducible() {
+        let (processed, dropped) = run_lan(7);
+        assert!(!processed.is_empty());
+        assert_eq!(run_lan(7), (processed.clone(), dropped));
+        assert!(run_lan(8).0 != processed);
+    }
+
+    #[test]
+    fn timing_conversion() {
+        // 802.3 at 1Mbps and a µs resolution; 96 and 512 bit-times and a 25.6µs delay.
+        let timing = Timing::new(1e6, 1e6);
+        assert_eq!((timing.sensing, timing.slot), (96, 512));
+        assert_eq!(timing.ticks(25.6e-6), 26);
+
+        // At a 10µs resolution every period is rounded up to the next whole tick.
+        let timing = Timing::new(1e5, 1e6);
+        assert_eq!((timing.sensing, timing.slot), (10, 52));
+        assert_eq!(timing.ticks(25.6e-6), 3);
+
+        // At 10Mbps and a 0.1µs resolution the bit-times shrink, physical delays do not.
+        let timing = Timing::new(1e7, 1e7);
+        assert_eq!((timing.sensing, timing.slot), (96, 512));
+        assert_eq!(timing.ticks(25.6e-6), 256);
+        assert_eq!(timing.bits_per_tick(), 1.0);
+    }
+
+    #[test]
+    fn medium_per_pair_delay() {
+        // Node 0 sits in between nodes 1 and 2, one and two ticks away respectively.
+        let mut med = Medium::with_delays(vec![vec![1, 1, 2], vec![1, 1, 3], vec![2, 3, 1]]);
+        let mut state = BitVec::from_elem(3, false);
+        state.set(0, true);
+
+        med.write(state);
+        med.tick();
+        assert!(med.is_busy(1));
+        assert!(!med.is_busy(2));
+
+        med.write(BitVec::from_elem(3, false));
+        med.tick();
+        assert!(!med.is_busy(1));
+        assert!(med.is_busy(2));
+
+        med.write(BitVec::from_elem(3, false));
+        med.tick();
+        assert!(!med.is_busy(1));
+        assert!(!med.is_busy(2));
+        assert!(!med.is_busy(0));
+    }
+
+    #[test]
+    fn server_tail_drop() {
+        // The Server generates a packet every tick but takes 100 ticks to transmit one, its queue
+        // of 2 quickly fills up.
+        let medium = Medium::new(1, 1);
+        let mut server = Server::new(
+            0, // id
+            Deterministic::new(1.0), // generator
+            Box::new(Constant(100)), // psize
+            Timing::new(1.0, 1.0), // timing
+            CsmaMode::NonPersistent, // csma
+            Some(2), // qsize
+            0, // seed
+        );
+        let mut state = BitVec::from_elem(1, false);
+        for i in 0..10 {
+            server.tick(&mut state, &medium, i);
+        }
+        assert_eq!(server.queue.len(), 2);
+        assert_eq!(server.packets_generated(), 10);
+        assert_eq!(server.statistics().packets_tail_dropped, 7);
+        assert_eq!(server.packets_dropped(), 0);
+    }
+
+    #[test]
+    fn parse_csma_mode() {
+        assert_eq!("nonpersistent".parse(), Ok(CsmaMode::NonPersistent));
+        assert_eq!("1".parse(), Ok(CsmaMode::OnePersistent));
+        assert_eq!("p=0.25".parse(), Ok(CsmaMode::PPersistent(0.25)));
+        assert_eq!(CsmaMode::PPersistent(0.25).to_string(), "p=0.25");
+        assert!("p=0".parse::<CsmaMode>().is_err());
+        assert!("p=1.5".parse::<CsmaMode>().is_err());
+        assert!("2".parse::<CsmaMode>().is_err());
+    }
+
+    #[test]
+    fn destinations() {
+        assert_eq!("uniform".parse(), Ok(Destinations::Uniform));
+        assert_eq!("broadcast".parse(), Ok(Destinations::Broadcast));
+        assert_eq!("3".parse(), Ok(Destinations::Node(3)));
+        assert!("anycast".parse::<Destinations>().is_err());
+
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
+        let mut drawn = [0; 4];
+        for _ in 0..1000 {
+            match Destinations::Uniform.next(&mut rng, 2, 4) {
+                Destination::Unicast(node) => drawn[node] += 1,
+                Destination::Broadcast => unreachable!(),
+            }
+        }
+        assert_eq!(drawn[2], 0);
+        assert!(drawn.iter().enumerate().all(|(node, &n)| node == 2 || n > 250));
+
+        let server = Destinations::Node(1);
+        assert_eq!(server.next(&mut rng, 0, 4), Destination::Unicast(1));
+        assert!(server.next(&mut rng, 1, 4) != Destination::Unicast(1));
+        assert_eq!(Destinations::Uniform.next(&mut rng, 0, 1), Destination::Broadcast);
+        assert!(Destination::Broadcast.reaches(0, 1));
+        assert!(!Destination::Broadcast.reaches(0, 0));
+    }
+
+    #[test]
+    fn medium_delivery() {
+        // Node 0 sends a packet to node 2, two ticks away, its last bit written at tick 1.
+        let mut medium = Medium::with_delays(vec![vec![0, 1, 2], vec![1, 0, 1], vec![2, 1, 0]]);
+        let packet = Packet {
+            id: 7,
+            source: 0,
+            destination: Destination::Unicast(2),
+            time_generated: 0,
+            length: 1,
+        };
+        let mut received = vec![];
+        for i in 0..5 {
+            if i == 1 {
+                medium.transmitted(packet);
+            }
+            for id in 0..3 {
+                for p in medium.received(id, i) {
+                    received.push((i, id, p.id));
+                }
+            }
+            medium.write(BitVec::from_elem(3, false));
+            medium.tick();
+        }
+        assert_eq!(received, vec![(3, 2, 7)]);
+        assert!(medium.frames.is_empty());
+    }
+
+    // server_retries returns the number of retries of the packet the Server is processing.
+    fn server_retries<G: Generator>(server: &Server<G>) -> u32 {
+        match server.mac.state {
+            ServerState::Idle => 0,
+            ServerState::Sensing { retries, .. } |
+            ServerState::Transmitting { retries, .. } |
+            ServerState::Jamming { retries, .. } |
+            ServerState::Waiting { retries, .. } => retries,
+        }
+    }
+
+    #[test]
+    fn persistent_sensing() {
+        // Node 1 keeps the medium busy for 1000 ticks, then leaves it idle for 200.
+        let run = |csma| {
+            let mut medium = Medium::new(2, 1);
+            let mut server = Server::new(
+                0, // id
+                Deterministic::new(0.01), // generator
+                Box::new(Constant(1)), // psize
+                Timing::new(1.0, 1.0), // timing
+                csma, // csma
+                None, // qsize
+                0, // seed
+            );
+            let mut retries = 0;
+            for i in 0..1200 {
+                if i == 1000 {
+                    retries = server_retries(&server);
+                }
+                let mut state = BitVec::from_elem(2, false);
+                state.set(1, i < 1000);
+                server.tick(&mut state, &medium, i);
+                medium.write(state);
+                medium.tick();
+            }
+            (retries, server.packets_processed())
+        };
+
+        // A non-persistent Server backs off on sensing the medium busy, a persistent one keeps
+        // sensing it and transmits once it's idle.
+        let (retries, _) = run(CsmaMode::NonPersistent);
+        assert!(retries > 0);
+        assert_eq!(run(CsmaMode::OnePersistent), (0, 1));
+        let (retries, processed) = run(CsmaMode::PPersistent(0.5));
+        assert_eq!(retries, 0);
+        assert!(processed <= 1);
+    }
+
+    // run_contended runs a non-persistent Server for the given number of ticks, node 1 keeping the
+    // medium busy for the first `busy` of them. Every time the Server is done with a packet,
+    // whether processed or dropped, it records the retries of the next packet the Server goes on
+    // to sense the medium for (or transmit), as of its first attempt.
+    fn run_contended(attempts: u32, busy: u32, ticks: u32) -> (Server<Deterministic>, Vec<u32>) {
+        let mut medium = Medium::new(2, 1);
+        let mut server = Server::new(
+            0, // id
+            Deterministic::new(0.01), // generator
+            Box::new(Constant(1)), // psize
+            Timing::new(1.0, 1.0), // timing
+            CsmaMode::NonPersistent, // csma
+            None, // qsize
+            0, // seed
+        ).with_attempts(attempts);
+        let mut done = 0;
+        let mut pending = false;
+        let mut next = vec![];
+        for i in 0..ticks {
+            let mut state = BitVec::from_elem(2, false);
+            state.set(1, i < busy);
+            server.tick(&mut state, &medium, i);
+            medium.write(state);
+            medium.tick();
+
+            let statistics = server.statistics();
+            if statistics.packets_processed + statistics.packets_dropped > done {
+                done = statistics.packets_processed + statistics.packets_dropped;
+                pending = true;
+            }
+            match server.mac.state {
+                ServerState::Sensing { .. } | ServerState::Transmitting { .. } if pending => {
+                    next.push(server_retries(&server));
+                    pending = false;
+                }
+                _ => {}
+            }
+        }
+        (server, next)
+    }
+
+    #[test]
+    fn backoff_resets_after_success() {
+        // The first packet backs off while the medium is busy; once it goes through, the packets
+        // queued up behind it start afresh.
+        let (server, next) = run_contended(MAX_ATTEMPTS, 250, 20000);
+        let statistics = server.statistics();
+        assert!(statistics.packets_processed > 2);
+        assert_eq!(statistics.packets_dropped, 0);
+        // Only the first packet was ever retried.
+        assert_eq!(statistics.retries[0], statistics.packets_processed - 1);
+        assert!(next.len() as u32 >= statistics.packets_processed - 1);
+        assert!(next.iter().all(|&r| r == 0));
+    }
+
+    #[test]
+    fn backoff_resets_after_drop() {
+        // With the medium busy throughout, every packet is dropped after its second failed
+        // attempt, the next one starting afresh.
+        let (server, next) = run_contended(2, 20000, 20000);
+        let statistics = server.statistics();
+        assert!(statistics.packets_dropped > 2);
+        assert_eq!(statistics.packets_processed, 0);
+        assert_eq!(next.len() as u32, statistics.packets_dropped);
+        assert!(next.iter().all(|&r| r == 0));
+    }
+
+    #[test]
+    fn collision_jams_medium() {
+        // Both Servers generate a packet at tick 100 and, once done sensing the medium, start
+        // transmitting at the same tick.
+        let timing = Timing::new(1.0, 1.0);
+        let mut medium = Medium::new(2, 1);
+        let mut servers: Vec<_> = (0..2)
+            .map(|id| {
+                Server::new(
+                    id, // id
+                    Deterministic::new(0.01), // generator
+                    Box::new(Constant(1000)), // psize
+                    timing, // timing
+                    CsmaMode::NonPersistent, // csma
+                    None, // qsize
+                    0, // seed
+                )
+            })
+            .collect();
+        let mut jammed = 0;
+        for i in 0..300 {
+            let mut state = BitVec::from_elem(2, false);
+            for server in servers.iter_mut() {
+                server.tick(&mut state, &medium, i);
+            }
+            let jammers = servers.iter().filter(|s| s.is_jamming()).count();
+            jammed = jammed.max(jammers);
+            medium.write_with_jam(state, jammers);
+            medium.tick();
+        }
+
+        // Each detects the collision a tick in, on the other's signal reaching it, and jams the
+        // medium for the duration of the jam signal.
+        assert_eq!(jammed, 2);
+        assert!(servers.iter().all(|s| s.statistics().collisions == 1));
+        let statistics = medium.statistics();
+        assert_eq!(statistics.jam_ticks, u64::from(timing.jam));
+        assert_eq!(statistics.collision_ticks, u64::from(timing.jam) + 1);
+        assert_eq!(statistics.wasted_ticks, statistics.collision_ticks);
+    }
+
+    #[test]
+    fn server_statistics_retries() {
+        let mut statistics = ServerStatistics::new();
+        assert_eq!(statistics.mean_retries(), 0.0);
+        statistics.processed(1000, 0.5, 0);
+        statistics.processed(1000, 1.5, 2);
+        statistics.processed(500, 1.0, 0);
+        assert_eq!(statistics.packets_processed, 3);
+        assert_eq!(statistics.bits_processed, 2500);
+        assert_eq!(statistics.retries, vec![2, 0, 1]);
+        assert_eq!(statistics.mean_retries(), 2.0 / 3.0);
+        assert_eq!(statistics.sojourn.mean(), 1.0);
+    }
+
+    #[test]
+    fn medium_channel_statistics() {
+        let mut med = Medium::new(8, 2);
+        for &state in &[0b00000000, 0b10000000, 0b01000000, 0b11000000, 0b00000000] {
+            med.write(BitVec::from_bytes(&[state]));
+            med.tick();
+        }
+
+        let statistics = med.statistics();
+        assert_eq!(statistics.idle_ticks, 2);
+        assert_eq!(statistics.busy_ticks, 2);
+        assert_eq!(statistics.collision_ticks, 1);
+        assert_eq!(statistics.utilization(), 0.4);
+        assert_eq!(statistics.collision_fraction(), 0.2);
+    }
+
+    // Every packet processed reaches its destinations a propagation delay after it's done being
+    // transmitted, but for those still in flight at the end of the run.
+    #[test]
+    fn delivery() {
+        let addressed = |dest| {
+            engine::run(&Params {
+                rate: 40,
+                psize: PacketSize::Constant(1000),
+                dest,
+                ncount: 4,
+                spacing: 2000.0,
+                qsize: Some(4),
+                duration: 1,
+                resolution: 1e5,
+                seed: 10,
+                ..Params::default()
+            })
+        };
+        for &dest in &[Destinations::Uniform, Destinations::Broadcast, Destinations::Node(1)] {
+            let report = addressed(dest);
+            let receivers = if dest == Destinations::Broadcast { 3 } else { 1 };
+            let processed = report.packets_processed() * receivers;
+            assert!(report.packets_received() <= processed);
+            assert!(report.packets_received() + receivers >= processed);
+            assert!(report.delay().mean() > report.sojourn.mean());
+        }
+
+        let server = addressed(Destinations::Node(1));
+        let clients = server.packets_processed() - server.statistics[1].packets_processed;
+        assert!(server.statistics[1].packets_received + 1 >= clients);
+        assert!(server.statistics[0].packets_received <= server.statistics[1].packets_processed);
+    }
 }
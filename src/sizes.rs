@@ -0,0 +1,417 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+use rand::distributions::{Exp, IndependentSample};
+use rand::{Rng, StdRng};
+use serde::{Serialize, Serializer};
+
+// ETHERNET_MIN_FRAME and ETHERNET_MAX_FRAME are the smallest and largest Ethernet frames, 64 and
+// 1518 bytes, in bits.
+pub const ETHERNET_MIN_FRAME: u32 = 64 * 8;
+pub const ETHERNET_MAX_FRAME: u32 = 1518 * 8;
+
+// SizeDistribution is the distribution of the length, in bits, of the packets a Client generates.
+// Like Generators, distributions draw from the RNG passed in by the caller.
+pub trait SizeDistribution {
+    // SizeDistribution.next_size draws the length of the next packet, at least a bit.
+    fn next_size(&self, rng: &mut StdRng) -> u32;
+}
+
+// Constant gives every packet the same length.
+pub struct Constant(pub u32);
+
+impl SizeDistribution for Constant {
+    fn next_size(&self, _rng: &mut StdRng) -> u32 {
+        self.0
+    }
+}
+
+// Uniform draws lengths uniformly from [min, max].
+pub struct Uniform {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl SizeDistribution for Uniform {
+    fn next_size(&self, rng: &mut StdRng) -> u32 {
+        rng.gen_range(self.min, self.max + 1)
+    }
+}
+
+// Exponential draws exponentially distributed lengths of the given mean, rounded up.
+pub struct Exponential {
+    exp: Exp,
+}
+
+impl Exponential {
+    pub fn new(mean: f64) -> Self {
+        Exponential { exp: Exp::new(1.0 / mean) }
+    }
+}
+
+impl SizeDistribution for Exponential {
+    fn next_size(&self, rng: &mut StdRng) -> u32 {
+        (self.exp.ind_sample(rng).ceil() as u32).max(1)
+    }
+}
+
+// Bimodal mixes small and large packets, the given fraction of them being small (think TCP
+// acknowledgements amidst full-sized segments).
+pub struct Bimodal {
+    pub small: u32,
+    pub large: u32,
+    pub fraction: f64,
+}
+
+impl SizeDistribution for Bimodal {
+    fn next_size(&self, rng: &mut StdRng) -> u32 {
+        if rng.gen::<f64>() < self.fraction {
+            self.small
+        } else {
+            self.large
+        }
+    }
+}
+
+// Empirical draws lengths from a histogram of (length, weight) pairs, in proportion to their
+// weights.
+pub struct Empirical {
+    sizes: Vec<u32>,
+    cumulative: Vec<f64>,
+}
+
+impl Empirical {
+    pub fn new(histogram: &[(u32, f64)]) -> Self {
+        let mut total = 0.0;
+        let cumulative = histogram
+            .iter()
+            .map(|&(_, weight)| {
+                total += weight;
+                total
+            })
+            .collect();
+        Empirical {
+            sizes: histogram.iter().map(|&(size, _)| size).collect(),
+            cumulative,
+        }
+    }
+}
+
+impl SizeDistribution for Empirical {
+    fn next_size(&self, rng: &mut StdRng) -> u32 {
+        let total = self.cumulative[self.cumulative.len() - 1];
+        let u = rng.gen::<f64>() * total;
+        let idx = self.cumulative.iter().position(|&c| u < c).unwrap_or(
+            self.sizes.len() - 1,
+        );
+        self.sizes[idx]
+    }
+}
+
+// Framed pads lengths short of the minimum Ethernet frame and caps those past the maximum one.
+pub struct Framed(pub Box<dyn SizeDistribution>);
+
+impl SizeDistribution for Framed {
+    fn next_size(&self, rng: &mut StdRng) -> u32 {
+        self.0.next_size(rng).clamp(ETHERNET_MIN_FRAME, ETHERNET_MAX_FRAME)
+    }
+}
+
+// PacketSize selects one of the SizeDistributions, as specified on the command line: 'N' for a
+// constant length, 'uniform=MIN,MAX', 'exponential=MEAN', 'bimodal=SMALL,LARGE,FRACTION' or
+// 'empirical=FILE', lengths being in bits. An empirical histogram is read from a file with a
+// 'LENGTH WEIGHT' pair per line, blank lines and those starting with '#' being skipped.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PacketSize {
+    Constant(u32),
+    Uniform { min: u32, max: u32 },
+    Exponential(f64),
+    Bimodal { small: u32, large: u32, fraction: f64 },
+    Empirical {
+        path: String,
+        histogram: Vec<(u32, f64)>,
+    },
+}
+
+impl PacketSize {
+    // PacketSize.build returns the SizeDistribution implementing the packet size, enforcing the
+    // Ethernet frame size limits if asked to.
+    pub fn build(&self, ethernet: bool) -> Box<dyn SizeDistribution> {
+        let sizes: Box<dyn SizeDistribution> = match *self {
+            PacketSize::Constant(size) => Box::new(Constant(size)),
+            PacketSize::Uniform { min, max } => Box::new(Uniform { min, max }),
+            PacketSize::Exponential(mean) => Box::new(Exponential::new(mean)),
+            PacketSize::Bimodal {
+                small,
+                large,
+                fraction,
+            } => Box::new(Bimodal {
+                small,
+                large,
+                fraction,
+            }),
+            PacketSize::Empirical { ref histogram, .. } => Box::new(Empirical::new(histogram)),
+        };
+        if ethernet { Box::new(Framed(sizes)) } else { sizes }
+    }
+
+    // PacketSize.mean returns the mean packet length, in bits, ignoring the Ethernet frame size
+    // limits.
+    pub fn mean(&self) -> f64 {
+        match *self {
+            PacketSize::Constant(size) => f64::from(size),
+            PacketSize::Uniform { min, max } => (f64::from(min) + f64::from(max)) / 2.0,
+            PacketSize::Exponential(mean) => mean,
+            PacketSize::Bimodal {
+                small,
+                large,
+                fraction,
+            } => fraction * f64::from(small) + (1.0 - fraction) * f64::from(large),
+            PacketSize::Empirical { ref histogram, .. } => {
+                let total: f64 = histogram.iter().map(|&(_, weight)| weight).sum();
+                histogram
+                    .iter()
+                    .map(|&(size, weight)| f64::from(size) * weight)
+                    .sum::<f64>() / total
+            }
+        }
+    }
+}
+
+// histogram reads an empirical histogram from the file at path.
+fn histogram(path: &str) -> Result<Vec<(u32, f64)>, String> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .map_err(|e| format!("unable to read packet sizes from '{}': {}", path, e))?;
+
+    let mut histogram = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || {
+            format!(
+                "invalid line {} of '{}', expected 'LENGTH WEIGHT' with a positive length and \
+                 a non-negative weight",
+                i + 1,
+                path
+            )
+        };
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() != 2 {
+            return Err(invalid());
+        }
+        let size = fields[0].parse::<u32>().map_err(|_| invalid())?;
+        let weight = fields[1].parse::<f64>().map_err(|_| invalid())?;
+        if size == 0 || weight < 0.0 || !weight.is_finite() {
+            return Err(invalid());
+        }
+        histogram.push((size, weight));
+    }
+    if histogram.iter().all(|&(_, weight)| weight == 0.0) {
+        return Err(format!("no packet sizes in '{}'", path));
+    }
+    Ok(histogram)
+}
+
+impl FromStr for PacketSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "unknown packet size '{}', expected 'N', 'uniform=MIN,MAX', 'exponential=MEAN', \
+                 'bimodal=SMALL,LARGE,FRACTION' or 'empirical=FILE' with positive lengths",
+                s
+            )
+        };
+        let size = |a: &str| match a.trim().parse::<u32>() {
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(invalid()),
+        };
+
+        if let Some(path) = s.strip_prefix("empirical=") {
+            return Ok(PacketSize::Empirical {
+                path: path.to_string(),
+                histogram: histogram(path)?,
+            });
+        }
+        if let Some(args) = s.strip_prefix("uniform=") {
+            let args: Vec<_> = args.split(',').collect();
+            if args.len() != 2 {
+                return Err(invalid());
+            }
+            let (min, max) = (size(args[0])?, size(args[1])?);
+            if min > max {
+                return Err(invalid());
+            }
+            return Ok(PacketSize::Uniform { min, max });
+        }
+        if let Some(mean) = s.strip_prefix("exponential=") {
+            return match mean.parse::<f64>() {
+                Ok(mean) if mean > 0.0 => Ok(PacketSize::Exponential(mean)),
+                _ => Err(invalid()),
+            };
+        }
+        if let Some(args) = s.strip_prefix("bimodal=") {
+            let args: Vec<_> = args.split(',').collect();
+            if args.len() != 3 {
+                return Err(invalid());
+            }
+            let fraction = args[2].trim().parse::<f64>().map_err(|_| invalid())?;
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(invalid());
+            }
+            return Ok(PacketSize::Bimodal {
+                small: size(args[0])?,
+                large: size(args[1])?,
+                fraction,
+            });
+        }
+        Ok(PacketSize::Constant(size(s)?))
+    }
+}
+
+impl fmt::Display for PacketSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PacketSize::Constant(size) => write!(f, "{}", size),
+            PacketSize::Uniform { min, max } => write!(f, "uniform={},{}", min, max),
+            PacketSize::Exponential(mean) => write!(f, "exponential={}", mean),
+            PacketSize::Bimodal {
+                small,
+                large,
+                fraction,
+            } => write!(f, "bimodal={},{},{}", small, large, fraction),
+            PacketSize::Empirical { ref path, .. } => write!(f, "empirical={}", path),
+        }
+    }
+}
+
+// Packet sizes serialize as they're specified on the command line, constant ones as numbers.
+impl Serialize for PacketSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            PacketSize::Constant(size) => serializer.serialize_u32(size),
+            _ => serializer.collect_str(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use engine::{self, Params};
+    use rng::{self, Stream};
+
+    fn draw(sizes: &PacketSize, ethernet: bool, n: usize) -> Vec<u32> {
+        let distribution = sizes.build(ethernet);
+        let mut rng = rng::stream(0, 0, Stream::Traffic);
+        (0..n).map(|_| distribution.next_size(&mut rng)).collect()
+    }
+
+    fn mean(sizes: &[u32]) -> f64 {
+        sizes.iter().map(|&s| f64::from(s)).sum::<f64>() / sizes.len() as f64
+    }
+
+    #[test]
+    fn distributions() {
+        assert_eq!(draw(&PacketSize::Constant(1000), false, 3), vec![1000; 3]);
+
+        let uniform = PacketSize::Uniform { min: 512, max: 1024 };
+        let sizes = draw(&uniform, false, 10000);
+        assert!(sizes.iter().all(|s| (512..=1024).contains(s)));
+        assert!(sizes.contains(&512) && sizes.contains(&1024));
+        assert!((mean(&sizes) - uniform.mean()).abs() < 10.0);
+
+        let exponential = PacketSize::Exponential(4000.0);
+        let sizes = draw(&exponential, false, 10000);
+        assert!(sizes.iter().all(|&s| s >= 1));
+        assert!((mean(&sizes) - 4000.0).abs() < 200.0);
+
+        let bimodal = PacketSize::Bimodal {
+            small: 512,
+            large: 12000,
+            fraction: 0.6,
+        };
+        let sizes = draw(&bimodal, false, 10000);
+        let small = sizes.iter().filter(|&&s| s == 512).count();
+        assert_eq!(sizes.iter().filter(|&&s| s == 12000).count(), 10000 - small);
+        assert!((small as f64 / 10000.0 - 0.6).abs() < 0.02);
+        assert_eq!(bimodal.mean(), 0.6 * 512.0 + 0.4 * 12000.0);
+    }
+
+    #[test]
+    fn ethernet_frames() {
+        let sizes = draw(&PacketSize::Exponential(4000.0), true, 10000);
+        assert!(sizes.contains(&ETHERNET_MIN_FRAME));
+        assert!(sizes.contains(&ETHERNET_MAX_FRAME));
+        assert!(sizes.iter().all(
+            |s| (ETHERNET_MIN_FRAME..=ETHERNET_MAX_FRAME).contains(s),
+        ));
+
+        // Packets are padded to the minimum Ethernet frame all the way onto the medium.
+        let report = engine::run(&Params {
+            rate: 40,
+            psize: PacketSize::Exponential(1000.0),
+            ethernet: true,
+            duration: 1,
+            resolution: 1e5,
+            seed: 5,
+            ..Params::default()
+        });
+        let padded = u64::from(report.packets_processed()) * u64::from(ETHERNET_MIN_FRAME);
+        assert!(report.bits_processed() >= padded);
+    }
+
+    #[test]
+    fn empirical_histogram() {
+        let path = env::temp_dir().join("nsim-sizes-test.txt");
+        {
+            let mut f = File::create(&path).unwrap();
+            writeln!(f, "# length weight").unwrap();
+            writeln!(f, "512 3").unwrap();
+            writeln!(f).unwrap();
+            writeln!(f, "12000 1").unwrap();
+        }
+        let spec = format!("empirical={}", path.display());
+        let sizes: PacketSize = spec.parse().unwrap();
+        assert_eq!(sizes.to_string(), spec);
+        assert_eq!(sizes.mean(), (3.0 * 512.0 + 12000.0) / 4.0);
+
+        let drawn = draw(&sizes, false, 10000);
+        let small = drawn.iter().filter(|&&s| s == 512).count();
+        assert!((small as f64 / 10000.0 - 0.75).abs() < 0.02);
+        assert!(drawn.iter().all(|&s| s == 512 || s == 12000));
+
+        File::create(&path).unwrap().write_all(b"512\n").unwrap();
+        assert!(spec.parse::<PacketSize>().is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(spec.parse::<PacketSize>().is_err());
+    }
+
+    #[test]
+    fn parse_packet_size() {
+        for &spec in &["1000", "uniform=512,12144", "exponential=4000", "bimodal=512,12000,0.6"] {
+            let sizes: PacketSize = spec.parse().unwrap();
+            assert_eq!(sizes.to_string(), spec);
+        }
+        for &spec in &[
+            "0",
+            "large",
+            "uniform=1024,512",
+            "uniform=512",
+            "exponential=0",
+            "bimodal=512,12000,1.5",
+        ]
+        {
+            assert!(spec.parse::<PacketSize>().is_err(), "{}", spec);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::str::FromStr;
+use engine::Params;
+
+// Sweep varies a single parameter over a set of values, specified either as an inclusive range,
+// NAME=FROM..TO:STEP (the step defaulting to 1), or as a list, NAME=V1,V2,... Every point of the
+// sweep is otherwise simulated with the same Params, including the seed.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Sweep {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl FromStr for Sweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, spec) = match s.find('=') {
+            Some(idx) => (&s[..idx], &s[idx + 1..]),
+            None => return Err(format!("invalid sweep '{}', expected NAME=VALUES", s)),
+        };
+
+        let values = if spec.contains("..") {
+            range(spec)?
+        } else {
+            spec.split(',').map(|v| v.trim().to_string()).collect()
+        };
+        if values.iter().any(|v| v.is_empty()) {
+            return Err(format!("invalid sweep values '{}'", spec));
+        }
+
+        Ok(Sweep {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+// range expands FROM..TO:STEP into the list of values it spans.
+fn range(spec: &str) -> Result<Vec<String>, String> {
+    let invalid = || format!("invalid sweep range '{}', expected FROM..TO:STEP", spec);
+    let parse = |x: &str| x.trim().parse::<f64>().map_err(|_| invalid());
+
+    let (bounds, step) = match spec.find(':') {
+        Some(idx) => (&spec[..idx], parse(&spec[idx + 1..])?),
+        None => (spec, 1.0),
+    };
+    let idx = bounds.find("..").unwrap();
+    let (from, to) = (parse(&bounds[..idx])?, parse(&bounds[idx + 2..])?);
+    if step <= 0.0 || from > to {
+        return Err(invalid());
+    }
+
+    // Computing every point from the start of the range keeps floating point error from
+    // accumulating, what remains is rounded off.
+    let points = ((to - from) / step + 1e-9).floor() as usize + 1;
+    Ok(
+        (0..points)
+            .map(|i| {
+                let value = from + i as f64 * step;
+                format!("{}", (value * 1e9).round() / 1e9)
+            })
+            .collect(),
+    )
+}
+
+impl Sweep {
+    // Sweep.params returns the Params for every point of the sweep, derived from the base Params,
+    // failing if any isn't valid (see Params.validate).
+    pub fn params(&self, base: &Params) -> Result<Vec<Params>, String> {
+        self.values
+            .iter()
+            .map(|value| {
+                let mut params = base.clone();
+                params.set(&self.name, value)?;
+                params.validate()?;
+                Ok(params)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sweep;
+
+    #[test]
+    fn parse_sweep() {
+        let sweep = "rate=1..5:2".parse::<Sweep>().unwrap();
+        assert_eq!(sweep.name, "rate");
+        assert_eq!(sweep.values, vec!["1", "3", "5"]);
+
+        let sweep = "spacing=0.1..0.3:0.1".parse::<Sweep>().unwrap();
+        assert_eq!(sweep.values, vec!["0.1", "0.2", "0.3"]);
+
+        let sweep = "ncount=20..22".parse::<Sweep>().unwrap();
+        assert_eq!(sweep.values, vec!["20", "21", "22"]);
+
+        let sweep = "ncount=20, 40,60".parse::<Sweep>().unwrap();
+        assert_eq!(sweep.values, vec!["20", "40", "60"]);
+
+        assert!("rate".parse::<Sweep>().is_err());
+        assert!("rate=".parse::<Sweep>().is_err());
+        assert!("rate=5..1".parse::<Sweep>().is_err());
+        assert!("rate=1..5:0".parse::<Sweep>().is_err());
+        assert!("rate=1..x".parse::<Sweep>().is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::VecDeque;
+use stats::OnlineStats;
+use engine::{Params, Report};
+use generators::Generator;
+use rng::{self, Stream};
+use simulators::{ChannelStatistics, Client, Packet, Queue, ServerStatistics, Timing};
+
+// PortStatistics is the set of statistics we care about post-simulation as far as an output port
+// of the Switch is concerned: the packets (and bits) it forwarded to the node it's linked to and
+// those dropped on arrival to its full buffer.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PortStatistics {
+    pub packets_forwarded: u32,
+    pub bits_forwarded: u64,
+    pub packets_port_dropped: u32,
+}
+
+// Link is one direction of a full-duplex link, transmitting the packets queued at its end one
+// after the other, an interframe gap apart. Nothing else ever transmits onto it, there's no
+// carrier to sense and no collision to detect.
+struct Link {
+    queue: Queue,
+    current: Option<(Packet, f64)>,
+    gap: u32,
+    busy: bool,
+}
+
+impl Link {
+    // Link::new returns an idle Link queueing at most limit packets, limit == None for an
+    // infinite queue.
+    fn new(limit: Option<usize>) -> Link {
+        Link {
+            queue: Queue::new(limit),
+            current: None,
+            gap: 0,
+            busy: false,
+        }
+    }
+
+    // Link.tick moves the Link one tick forward, returning the packet whose last bit it
+    // transmitted this tick, if any.
+    fn tick(&mut self, timing: &Timing) -> Option<Packet> {
+        self.busy = false;
+        if self.current.is_none() {
+            if self.gap > 0 {
+                self.gap -= 1;
+                return None;
+            }
+            self.current = self.queue.pop().map(|packet| (packet, 0.0));
+        }
+
+        let (packet, bits_processed) = self.current?;
+        let bits_processed = bits_processed + timing.bits_per_tick();
+        self.busy = true;
+        if (bits_processed as u32) >= packet.length {
+            self.current = None;
+            self.gap = timing.sensing;
+            return Some(packet);
+        }
+        self.current = Some((packet, bits_processed));
+        None
+    }
+}
+
+// Host is a node attached to the Switch: its Client, the link up to the Switch its packets are
+// queued at and its statistics, gathered as a Server's would be.
+struct Host<G: Generator> {
+    client: Client<G>,
+    uplink: Link,
+    statistics: ServerStatistics,
+}
+
+// Switch simulates a switched LAN, tick by tick. Every node is attached to a store-and-forward
+// switch over a dedicated full-duplex link, Params.spacing meters long, and so never contends for
+// it. A packet is forwarded once the switch has received it whole, the forwarding latency after
+// its last bit reached the switch, being queued at the output port of the node it's addressed to
+// (every other node's, for broadcasts). Output ports buffer at most Params.pbuffer packets,
+// dropping those arriving to a full buffer. Nodes draw from the same RNG streams as they would
+// on the bus, the workload being identical for the same seed.
+//
+// Packets in flight are kept in the order they're due at the switch (forwarding) or at the nodes
+// (delivering), every link having the same propagation delay.
+pub struct Switch<G: Generator> {
+    hosts: Vec<Host<G>>,
+    ports: Vec<Link>,
+    statistics: Vec<PortStatistics>,
+    forwarding: VecDeque<(u32, Packet)>,
+    delivering: VecDeque<(u32, usize, Packet)>,
+    delay: u32,
+    latency: u32,
+    ticks: u32,
+    warmup: u32,
+    timing: Timing,
+}
+
+impl Switch<Box<dyn Generator>> {
+    // Switch::new returns a Switch for the LAN described by params.
+    pub fn new(params: &Params) -> Self {
+        let timing = params.timing();
+        let hosts = (0..params.ncount)
+            .map(|id| {
+                let node = params.node(id);
+                Host {
+                    client: Client::new(
+                        node.traffic.build(f64::from(node.rate), id),
+                        rng::stream(params.seed, id, Stream::Traffic),
+                        timing.resolution,
+                        node.psize.build(params.ethernet),
+                    ).with_destinations(id, params.ncount, node.dest),
+                    uplink: Link::new(node.qsize),
+                    statistics: ServerStatistics::new(),
+                }
+            })
+            .collect();
+        Switch {
+            hosts,
+            ports: (0..params.ncount).map(|_| Link::new(params.pbuffer)).collect(),
+            statistics: vec![PortStatistics::default(); params.ncount],
+            forwarding: VecDeque::new(),
+            delivering: VecDeque::new(),
+            delay: timing.ticks(params.spacing / params.speed),
+            latency: if params.latency > 0.0 {
+                timing.ticks(params.latency)
+            } else {
+                0
+            },
+            ticks: params.ticks(),
+            warmup: params.warmup_ticks(),
+            timing,
+        }
+    }
+}
+
+impl<G: Generator> Switch<G> {
+    // Switch.run simulates every tick until the end of the simulation. Within a tick, packets
+    // reach the nodes and the switch first, then nodes generate packets and every link transmits.
+    pub fn run(mut self) -> Report {
+        let mut sojourn = OnlineStats::new();
+        let mut channel = ChannelStatistics::new();
+        let nodes = self.hosts.len();
+
+        for i in 0..self.ticks {
+            if i == self.warmup {
+                for host in self.hosts.iter_mut() {
+                    host.statistics = ServerStatistics::new();
+                }
+                self.statistics = vec![PortStatistics::default(); nodes];
+                sojourn = OnlineStats::new();
+                channel = ChannelStatistics::new();
+            }
+
+            while self.delivering.front().is_some_and(|&(tick, _, _)| tick == i) {
+                let (_, id, packet) = self.delivering.pop_front().unwrap();
+                let delay = self.timing.seconds(i - packet.time_generated);
+                self.hosts[id].statistics.received(packet.length, delay);
+            }
+            while self.forwarding.front().is_some_and(|&(tick, _)| tick == i) {
+                let (_, packet) = self.forwarding.pop_front().unwrap();
+                for id in (0..nodes).filter(|&id| packet.destination.reaches(packet.source, id)) {
+                    if !self.ports[id].queue.push(packet) {
+                        self.statistics[id].packets_port_dropped += 1;
+                    }
+                }
+            }
+
+            let mut busy = 0;
+            for host in self.hosts.iter_mut() {
+                if let Some(packet) = host.client.tick(i) {
+                    host.statistics.packets_generated += 1;
+                    if !host.uplink.queue.push(packet) {
+                        host.statistics.packets_tail_dropped += 1;
+                    }
+                }
+                if let Some(packet) = host.uplink.tick(&self.timing) {
+                    let seconds = self.timing.seconds(i - packet.time_generated);
+                    host.statistics.processed(packet.length, seconds, 0);
+                    sojourn.add(seconds);
+                    self.forwarding.push_back((i + self.delay + self.latency, packet));
+                }
+                if host.uplink.busy {
+                    busy += 1;
+                }
+            }
+            for (id, port) in self.ports.iter_mut().enumerate() {
+                if let Some(packet) = port.tick(&self.timing) {
+                    self.statistics[id].packets_forwarded += 1;
+                    self.statistics[id].bits_forwarded += u64::from(packet.length);
+                    self.delivering.push_back((i + self.delay, id, packet));
+                }
+            }
+            channel.record(1, 0, busy);
+            channel.record(0, 0, nodes as u64 - busy);
+        }
+
+        Report {
+            sojourn,
+            statistics: self.hosts.into_iter().map(|h| h.statistics).collect(),
+            channel,
+            duration: self.timing.seconds(self.ticks - self.warmup),
+            lspeed: self.timing.lspeed,
+            series: vec![],
+            log: vec![],
+            ports: self.statistics,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::{self, Lan, Params};
+    use simulators::Destinations;
+    use sizes::PacketSize;
+
+    fn params(lan: Lan) -> Params {
+        Params {
+            rate: 100,
+            psize: PacketSize::Constant(1000),
+            duration: 1,
+            resolution: 1e5,
+            lan,
+            seed: 11,
+            ..Params::default()
+        }
+    }
+
+    // Under a load the bus can't carry, the switch delivers every packet with neither
+    // collisions nor queueing to speak of.
+    #[test]
+    fn hub_and_switch() {
+        let lan = |lan| engine::run(&Params { seed: 1, ..params(lan) });
+        let (hub, switch) = (lan(Lan::Bus), lan(Lan::Switch));
+        assert_eq!(hub.packets_generated(), switch.packets_generated());
+        assert!(hub.collisions() > 0);
+        assert_eq!(switch.collisions(), 0);
+        assert_eq!(switch.channel.collision_fraction(), 0.0);
+        assert!(switch.throughput() > hub.throughput());
+        assert!(switch.delay().mean() < hub.delay().mean());
+        assert!(switch.packets_processed() + 10 >= switch.packets_generated());
+        assert!(switch.packets_received() + 20 >= switch.packets_processed());
+
+        // Packets take 1ms to transmit over either link, stored and forwarded whole.
+        assert!(switch.delay().mean() > 0.002);
+        let forwarded: u32 = switch.ports.iter().map(|p| p.packets_forwarded).sum();
+        assert!(forwarded >= switch.packets_received());
+        assert!(hub.ports.is_empty());
+    }
+
+    #[test]
+    fn port_buffers() {
+        // Every node sends to node 0, whose port gets three times the traffic it can forward.
+        let congested = |pbuffer, latency| {
+            engine::run(&Params {
+                rate: 300,
+                ncount: 4,
+                dest: Destinations::Node(0),
+                pbuffer,
+                latency,
+                ..params(Lan::Switch)
+            })
+        };
+        let bounded = congested(Some(8), 0.0);
+        let port = &bounded.ports[0];
+        assert!(port.packets_port_dropped > 0);
+        assert!(bounded.ports[1..].iter().all(|p| p.packets_port_dropped == 0));
+        assert!(port.packets_forwarded >= bounded.statistics[0].packets_received);
+        assert!(port.packets_forwarded <= 1000);
+
+        // The unbounded buffer drops nothing and builds up a backlog.
+        let unbounded = congested(None, 0.0);
+        assert_eq!(unbounded.ports[0].packets_port_dropped, 0);
+        assert!(unbounded.delay().mean() > bounded.delay().mean());
+
+        // Node 1 only receives node 0's packets, over a port the latency merely delays them at.
+        let slow = congested(Some(8), 0.01);
+        let delay = slow.statistics[1].delay.mean() - bounded.statistics[1].delay.mean();
+        assert!((delay - 0.01).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,71 @@
+use simulators::Timing;
+
+// Topology describes the physical layout of the LAN: where each node is attached to the bus, in
+// meters from one end, and the speed at which signals propagate along it, in meters/s.
+#[derive(Clone, Debug)]
+pub struct Topology {
+    positions: Vec<f64>,
+    speed: f64,
+}
+
+impl Topology {
+    // Topology::new returns a Topology with nodes attached at the given positions.
+    pub fn new(positions: Vec<f64>, speed: f64) -> Topology {
+        assert!(speed > 0.0);
+        Topology { positions, speed }
+    }
+
+    // Topology::bus returns a Topology with num_nodes nodes attached to the bus at equal spacing,
+    // node 0 sitting at one end.
+    pub fn bus(num_nodes: usize, spacing: f64, speed: f64) -> Topology {
+        Topology::new((0..num_nodes).map(|i| i as f64 * spacing).collect(), speed)
+    }
+
+    // Topology.length returns the length of bus spanned by the nodes, in meters.
+    pub fn length(&self) -> f64 {
+        let min = self.positions.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = self.positions.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        if self.positions.is_empty() {
+            0.0
+        } else {
+            max - min
+        }
+    }
+
+    // Topology.delay returns the time, in seconds, it takes a signal to propagate from one node to
+    // another.
+    pub fn delay(&self, from: usize, to: usize) -> f64 {
+        (self.positions[from] - self.positions[to]).abs() / self.speed
+    }
+
+    // Topology.delays returns the propagation delay between every pair of nodes in ticks, indexed
+    // by receiver and then sender. Every delay is at least a tick, a node cannot observe the
+    // medium being written to within the same tick.
+    pub fn delays(&self, timing: &Timing) -> Vec<Vec<u32>> {
+        (0..self.positions.len())
+            .map(|to| {
+                (0..self.positions.len())
+                    .map(|from| timing.ticks(self.delay(from, to)))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Topology;
+    use simulators::Timing;
+
+    #[test]
+    fn bus_delays() {
+        // Three nodes 1km apart, at 2e8 m/s signals take 5µs to get from one to the next.
+        let topology = Topology::bus(3, 1000.0, 2e8);
+        assert_eq!(topology.length(), 2000.0);
+        assert_eq!(topology.delay(0, 2), 1e-5);
+        assert_eq!(topology.delay(2, 0), topology.delay(0, 2));
+
+        let delays = topology.delays(&Timing::new(1e6, 1e6));
+        assert_eq!(delays, vec![vec![1, 5, 10], vec![5, 1, 5], vec![10, 5, 1]]);
+    }
+}
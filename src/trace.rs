@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+// Record is a packet in a traffic trace: the time it's generated at, in seconds since the start
+// of the trace, the node generating it and its length in bits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Record {
+    pub time: f64,
+    pub node: usize,
+    pub length: u32,
+}
+
+// trace::load reads the trace at path, either a pcap capture or a CSV file of 'time,node,length'
+// rows (see trace::csv and trace::pcap). Records are returned in order of time.
+pub fn load(path: &str) -> Result<Vec<Record>, String> {
+    let mut contents = vec![];
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut contents))
+        .map_err(|e| format!("unable to read trace '{}': {}", path, e))?;
+
+    let mut records = if pcap_order(&contents).is_some() {
+        pcap(&contents)
+    } else {
+        String::from_utf8(contents)
+            .map_err(|_| "neither a pcap capture nor a CSV file".to_string())
+            .and_then(|contents| csv(&contents))
+    }.map_err(|e| format!("invalid trace '{}': {}", path, e))?;
+    if records.is_empty() {
+        return Err(format!("invalid trace '{}': no packets", path));
+    }
+    records.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+    Ok(records)
+}
+
+// trace::csv parses 'time,node,length' rows, the time in seconds and the length in bits. A header
+// row, blank lines and lines starting with '#' are skipped.
+fn csv(contents: &str) -> Result<Vec<Record>, String> {
+    let mut records = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (i == 0 && line.starts_with("time")) {
+            continue;
+        }
+        let invalid = || {
+            format!(
+                "line {}, expected 'time,node,length' with a non-negative time and a positive \
+                 length",
+                i + 1
+            )
+        };
+        let fields: Vec<_> = line.split(',').map(|f| f.trim()).collect();
+        if fields.len() != 3 {
+            return Err(invalid());
+        }
+        let record = Record {
+            time: fields[0].parse().map_err(|_| invalid())?,
+            node: fields[1].parse().map_err(|_| invalid())?,
+            length: fields[2].parse().map_err(|_| invalid())?,
+        };
+        if !(record.time >= 0.0 && record.time.is_finite()) || record.length == 0 {
+            return Err(invalid());
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// PCAP_ETHERNET is the pcap link-layer header type of Ethernet captures.
+const PCAP_ETHERNET: u32 = 1;
+
+// pcap_order returns whether the capture's fields are little-endian and its timestamps in
+// nanoseconds (rather than microseconds), going by the magic number of the pcap global header.
+// It returns None for anything that isn't a pcap capture.
+fn pcap_order(contents: &[u8]) -> Option<(bool, bool)> {
+    if contents.len() < 4 {
+        return None;
+    }
+    let magic = [contents[0], contents[1], contents[2], contents[3]];
+    match u32::from_le_bytes(magic) {
+        0xa1b2_c3d4 => Some((true, false)),
+        0xa1b2_3c4d => Some((true, true)),
+        _ => match u32::from_be_bytes(magic) {
+            0xa1b2_c3d4 => Some((false, false)),
+            0xa1b2_3c4d => Some((false, true)),
+            _ => None,
+        },
+    }
+}
+
+// trace::pcap parses an Ethernet pcap capture. Nodes are identified by source MAC address,
+// numbered in the order they first appear in, the length of a packet is its length on the wire
+// and times are relative to the first packet's.
+fn pcap(contents: &[u8]) -> Result<Vec<Record>, String> {
+    let (little_endian, nanos) = pcap_order(contents).unwrap();
+    let word = |offset: usize| -> Result<u32, String> {
+        if offset + 4 > contents.len() {
+            return Err("truncated pcap capture".to_string());
+        }
+        let bytes = [
+            contents[offset],
+            contents[offset + 1],
+            contents[offset + 2],
+            contents[offset + 3],
+        ];
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    if word(20)? != PCAP_ETHERNET {
+        return Err("only Ethernet pcap captures are supported".to_string());
+    }
+
+    let mut nodes = HashMap::new();
+    let mut records = vec![];
+    let mut start = None;
+    let mut offset = 24;
+    while offset < contents.len() {
+        let seconds = f64::from(word(offset)?);
+        let fraction = f64::from(word(offset + 4)?) / if nanos { 1e9 } else { 1e6 };
+        let captured = word(offset + 8)? as usize;
+        let length = word(offset + 12)?;
+        let frame = offset + 16;
+        if captured < 12 || frame + captured > contents.len() {
+            return Err("truncated pcap capture".to_string());
+        }
+
+        let time = seconds + fraction;
+        let start = *start.get_or_insert(time);
+        let count = nodes.len();
+        let source = &contents[frame + 6..frame + 12];
+        let node = *nodes.entry(source).or_insert(count);
+        records.push(Record {
+            time: time - start,
+            node,
+            length: length * 8,
+        });
+        offset = frame + captured;
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use engine::{self, Params};
+    use generators::Traffic;
+
+    // write writes contents to a temporary file, returning its path.
+    fn write(name: &str, contents: &[u8]) -> String {
+        let path = env::temp_dir().join(name);
+        File::create(&path).unwrap().write_all(contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn csv_trace() {
+        let path = write(
+            "nsim-trace-test.csv",
+            b"time,node,length\n0.5,1,1000\n# comment\n0.25, 0, 512\n",
+        );
+        assert_eq!(
+            load(&path).unwrap(),
+            vec![
+                Record {
+                    time: 0.25,
+                    node: 0,
+                    length: 512,
+                },
+                Record {
+                    time: 0.5,
+                    node: 1,
+                    length: 1000,
+                },
+            ]
+        );
+
+        for contents in &[&b"0.5,1\n"[..], b"0.5,1,0\n", b"-1,0,512\n", b"time,node,length\n"] {
+            File::create(&path).unwrap().write_all(contents).unwrap();
+            assert!(load(&path).is_err());
+        }
+        fs::remove_file(&path).unwrap();
+        assert!(load(&path).is_err());
+    }
+
+    #[test]
+    fn pcap_trace() {
+        // A big-endian capture of three frames, from two distinct source MAC addresses.
+        let mut capture = vec![];
+        for &word in &[0xa1b2_c3d4u32, 0x0002_0004, 0, 0, 65535, PCAP_ETHERNET] {
+            capture.extend_from_slice(&word.to_be_bytes());
+        }
+        for &(seconds, micros, source, length) in
+            &[(100u32, 500_000u32, 0xaa, 64u32), (101, 0, 0xbb, 1518), (101, 250_000, 0xaa, 100)]
+        {
+            for &word in &[seconds, micros, 14, length] {
+                capture.extend_from_slice(&word.to_be_bytes());
+            }
+            capture.extend_from_slice(&[0xff; 6]);
+            capture.extend_from_slice(&[source; 6]);
+            capture.extend_from_slice(&[0x08, 0x00]);
+        }
+
+        let path = write("nsim-trace-test.pcap", &capture);
+        let records = load(&path).unwrap();
+        let summary: Vec<_> = records.iter().map(|r| (r.time, r.node, r.length)).collect();
+        assert_eq!(summary, vec![(0.0, 0, 512), (0.5, 1, 12144), (0.75, 0, 800)]);
+
+        // Truncated captures are rejected.
+        File::create(&path).unwrap().write_all(&capture[..capture.len() - 1]).unwrap();
+        assert!(load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    // A LAN replaying a trace generates and processes the packets of the trace alone.
+    #[test]
+    fn replay() {
+        // Nodes 0 and 2 replay 200 packets between them, node 1 stays silent.
+        let records: Vec<_> = (0..200u32)
+            .map(|i| {
+                Record {
+                    time: f64::from(i) * 0.004,
+                    node: (i as usize % 2) * 2,
+                    length: 1000 + i,
+                }
+            })
+            .collect();
+        let report = engine::run(&Params {
+            traffic: Traffic::Trace {
+                path: "trace.csv".to_string(),
+                records: records.clone(),
+            },
+            ncount: 3,
+            duration: 1,
+            resolution: 1e5,
+            seed: 6,
+            ..Params::default()
+        });
+        assert_eq!(report.packets_generated(), 200);
+        assert_eq!(report.statistics[1].packets_generated, 0);
+        assert_eq!(report.packets_processed(), 200);
+        assert_eq!(
+            report.bits_processed(),
+            records.iter().map(|r| u64::from(r.length)).sum::<u64>()
+        );
+    }
+}